@@ -0,0 +1,18 @@
+use std::process::Command;
+
+fn command_output(program: &str, args: &[&str]) -> String {
+    Command::new(program).args(args).output().ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|output| output.trim().to_string())
+        .unwrap_or_default()
+}
+
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    println!("cargo:rustc-env=WEATHER_GIT_COMMIT={}", command_output("git", &["rev-parse", "--short", "HEAD"]));
+    println!("cargo:rustc-env=WEATHER_RUSTC_VERSION={}", command_output(&rustc, &["--version"]));
+    println!("cargo:rustc-env=WEATHER_TARGET={}", std::env::var("TARGET").unwrap_or_default());
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
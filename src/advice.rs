@@ -0,0 +1,317 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use chrono::{NaiveDate, NaiveTime};
+use crate::raw::Forecast;
+use crate::status::WeatherStatus;
+use crate::DayWrapper;
+use crate::units;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UvLevel {
+    High,
+    VeryHigh,
+    Extreme
+}
+
+impl UvLevel {
+    fn from_index(uv_index: f32) -> Option<UvLevel> {
+        if uv_index >= 11.0 {
+            Some(UvLevel::Extreme)
+        } else if uv_index >= 8.0 {
+            Some(UvLevel::VeryHigh)
+        } else if uv_index >= 6.0 {
+            Some(UvLevel::High)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SunAdvice {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub peak_uv: f32,
+    pub level: UvLevel,
+    pub advice: String
+}
+
+impl fmt::Display for SunAdvice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let level = match self.level {
+            UvLevel::High => "High",
+            UvLevel::VeryHigh => "Very high",
+            UvLevel::Extreme => "Extreme"
+        };
+        write!(f, "{} UV {}-{}, {}", level, self.start.format("%H:%M"), self.end.format("%H:%M"), self.advice)
+    }
+}
+
+/// Finds the windows of a day's hourly forecasts where the UV index is high or above,
+/// ignoring hours outside of daylight if the sunrise and sunset are known.
+pub fn sun_advice(hourly: &[(NaiveTime, Forecast)], daylight: Option<(NaiveTime, NaiveTime)>) -> Vec<SunAdvice> {
+    let mut windows: Vec<Vec<&(NaiveTime, Forecast)>> = Vec::new();
+    let mut last: Option<NaiveTime> = None;
+    for hour in hourly {
+        let (time, forecast) = hour;
+        let in_daylight = daylight.is_none_or(|(sunrise, sunset)| *time >= sunrise && *time <= sunset);
+        if !in_daylight || forecast.uv_index.and_then(UvLevel::from_index).is_none() {
+            continue
+        }
+
+        match (windows.last_mut(), last) {
+            (Some(window), Some(last)) if *time - last == chrono::Duration::hours(1) => window.push(hour),
+            _ => windows.push(vec![hour])
+        }
+        last = Some(*time);
+    }
+
+    windows.into_iter().filter_map(|window| {
+        let start = window.first()?.0;
+        // A window that runs until midnight ends at the end of the day, rather than wrapping around to its start.
+        let (end, wrapped) = window.last()?.0.overflowing_add_signed(chrono::Duration::hours(1));
+        let end = if wrapped != 0 { NaiveTime::from_hms(23, 59, 59) } else { end };
+        let peak_uv = window.iter().filter_map(|(_, forecast)| forecast.uv_index).fold(0.0, f32::max);
+        let level = UvLevel::from_index(peak_uv)?;
+        let cloudy = window.iter().any(|(_, forecast)| matches!(forecast.status, WeatherStatus::Cloudy | WeatherStatus::Overcast));
+
+        let mut advice = match level {
+            UvLevel::High => "use SPF30+",
+            UvLevel::VeryHigh => "use SPF50+ and cover up",
+            UvLevel::Extreme => "use SPF50+ and avoid the midday sun"
+        }.to_string();
+        if cloudy {
+            advice.push_str(", even under cloud");
+        }
+
+        Some(SunAdvice { start, end, peak_uv, level, advice })
+    }).collect()
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct BestDayWeights {
+    pub precipitation: f32,
+    pub temperature: f32,
+    pub wind: f32,
+    pub uv: f32,
+    pub ideal_temperature: f32,
+    pub max_uv: f32
+}
+
+impl Default for BestDayWeights {
+    fn default() -> BestDayWeights {
+        BestDayWeights { precipitation: 1.0, temperature: 1.0, wind: 1.0, uv: 0.5, ideal_temperature: 20.0, max_uv: 6.0 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct BestDay {
+    pub date: NaiveDate,
+    pub score: f32,
+    pub precipitation: f32,
+    pub temperature: f32,
+    pub wind_speed: f32,
+    pub uv_index: f32
+}
+
+fn mean(values: impl Iterator<Item = f32>) -> Option<f32> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    if count == 0 { None } else { Some(sum / count as f32) }
+}
+
+/// Scores each day from its forecast times and picks the highest. Temperatures and wind
+/// speeds are compared in metric units, so the weights don't depend on the unit system.
+/// Missing values are left out, and days missing a whole field aren't scored.
+pub fn best_day(data: &[DayWrapper], weights: &BestDayWeights, freedom_units: bool) -> Option<BestDay> {
+    let to_celsius = |t: f32| units::to_celsius(t, freedom_units);
+    let to_kph = |s: f32| units::to_kph(s, freedom_units);
+
+    data.iter().filter_map(|day| {
+        let forecasts = || day.times.iter().map(|time| &time.forecast);
+        let precipitation = mean(forecasts().filter_map(|f| f.precipitation))?;
+        let temperature = mean(forecasts().filter_map(|f| f.temperature))?;
+        let wind_speed = mean(forecasts().filter_map(|f| f.wind_speed))?;
+        let uv_index = forecasts().filter_map(|f| f.uv_index).fold(0.0, f32::max);
+
+        let score = weights.uv * uv_index.min(weights.max_uv)
+            - weights.precipitation * precipitation / 10.0
+            - weights.temperature * (to_celsius(temperature) - weights.ideal_temperature).abs() / 2.0
+            - weights.wind * to_kph(wind_speed) / 5.0;
+
+        Some(BestDay { date: day.date, score, precipitation, temperature, wind_speed, uv_index })
+    }).max_by(|a, b| a.score.total_cmp(&b.score))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Clothing {
+    HeavyCoat,
+    WarmCoat,
+    WarmLayer,
+    LightLayer,
+    Waterproof,
+    Umbrella,
+    Windproof,
+    Boots
+}
+
+impl Clothing {
+    pub fn label(self) -> &'static str {
+        match self {
+            Clothing::HeavyCoat => "a heavy coat, hat and gloves",
+            Clothing::WarmCoat => "a warm coat",
+            Clothing::WarmLayer => "a warm layer",
+            Clothing::LightLayer => "a light layer",
+            Clothing::Waterproof => "a waterproof",
+            Clothing::Umbrella => "an umbrella just in case",
+            Clothing::Windproof => "something windproof",
+            Clothing::Boots => "waterproof boots"
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct ClothingAdvice {
+    pub items: Vec<Clothing>,
+    pub text: String
+}
+
+/// Bands of the coldest feels-like temperature in Celsius, and the layer to take for each.
+const TEMPERATURE_BANDS: &[(f32, Clothing)] = &[
+    (0.0, Clothing::HeavyCoat),
+    (8.0, Clothing::WarmCoat),
+    (14.0, Clothing::WarmLayer),
+    (20.0, Clothing::LightLayer)
+];
+
+/// Bands of the highest precipitation chance, and what to take for each.
+const RAIN_BANDS: &[(f32, Clothing)] = &[
+    (60.0, Clothing::Waterproof),
+    (30.0, Clothing::Umbrella)
+];
+
+pub fn clothing_advice(day: &DayWrapper, freedom_units: bool) -> Option<ClothingAdvice> {
+    let to_celsius = |t: f32| units::to_celsius(t, freedom_units);
+    let to_kph = |s: f32| units::to_kph(s, freedom_units);
+
+    let forecasts = || day.times.iter().map(|time| &time.forecast);
+    let coldest = forecasts().filter_map(|f| f.feels_like.or(f.temperature)).map(to_celsius).reduce(f32::min)?;
+    let wettest = forecasts().filter_map(|f| f.precipitation).fold(0.0, f32::max);
+    let windiest = forecasts().flat_map(|f| [f.wind_speed, f.wind_gust]).flatten().map(to_kph).fold(0.0, f32::max);
+    let snowy = forecasts().any(|f| matches!(f.status, WeatherStatus::LightSnow | WeatherStatus::HeavySnow | WeatherStatus::Sleet));
+
+    let mut items = Vec::new();
+    items.extend(RAIN_BANDS.iter().find(|(chance, _)| wettest >= *chance).map(|(_, item)| *item));
+    items.extend(TEMPERATURE_BANDS.iter().find(|(limit, _)| coldest < *limit).map(|(_, item)| *item));
+    if windiest >= 40.0 && !items.contains(&Clothing::Waterproof) {
+        items.push(Clothing::Windproof);
+    }
+
+    let mut text = match items.as_slice() {
+        [] => "No special clothing needed.".to_string(),
+        [item] => format!("Take {}.", item.label()),
+        [init @ .., last] => {
+            let init: Vec<&str> = init.iter().map(|item| item.label()).collect();
+            format!("Take {} and {}.", init.join(", "), last.label())
+        }
+    };
+
+    if snowy {
+        items.push(Clothing::Boots);
+        text.push_str(&format!(" Wear {}.", Clothing::Boots.label()));
+    }
+
+    Some(ClothingAdvice { items, text })
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct GustThresholds {
+    pub amber: f32,
+    pub red: f32
+}
+
+impl Default for GustThresholds {
+    fn default() -> GustThresholds {
+        GustThresholds { amber: 40.0, red: 60.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GustSeverity {
+    Amber,
+    Red
+}
+
+/// Compares a gust against the thresholds, which are given in kilometres per hour.
+pub fn gust_severity(gust: f32, thresholds: &GustThresholds, freedom_units: bool) -> Option<GustSeverity> {
+    let gust = units::to_kph(gust, freedom_units);
+    if gust > thresholds.red {
+        Some(GustSeverity::Red)
+    } else if gust > thresholds.amber {
+        Some(GustSeverity::Amber)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sun_advice_ends_at_the_end_of_the_day() {
+        let hourly = vec![(NaiveTime::from_hms(23, 0, 0), Forecast { uv_index: Some(7.0), ..Forecast::default() })];
+        let advice = sun_advice(&hourly, None);
+        assert_eq!((advice[0].start, advice[0].end), (NaiveTime::from_hms(23, 0, 0), NaiveTime::from_hms(23, 59, 59)));
+    }
+
+    fn day(date: u32, forecasts: Vec<Forecast>) -> DayWrapper {
+        DayWrapper {
+            date: NaiveDate::from_ymd(2026, 1, date), summary: None, daylight: None, tides: Vec::new(), sea_temperature: None,
+            pressure_trend: None, sun_advice: Vec::new(), advice: None, snow: None, wet_hours: 0,
+            times: forecasts.into_iter().enumerate().map(|(hour, forecast)| crate::TimeWrapper::new(NaiveTime::from_hms(hour as u32, 0, 0), forecast)).collect()
+        }
+    }
+
+    #[test]
+    fn best_day_is_the_highest_scoring_day_with_every_field() {
+        let at = |precipitation: f32, temperature: f32, wind_speed: Option<f32>| Forecast {
+            precipitation: Some(precipitation), temperature: Some(temperature), wind_speed, uv_index: Some(3.0), ..Forecast::default()
+        };
+        let data = vec![
+            day(14, vec![at(80.0, 12.0, Some(30.0)), at(60.0, 14.0, Some(25.0))]),
+            day(15, vec![at(10.0, 19.0, Some(10.0)), at(0.0, 21.0, Some(10.0))]),
+            day(16, vec![at(0.0, 20.0, None)])
+        ];
+        let best = best_day(&data, &BestDayWeights::default(), false).unwrap();
+        assert_eq!(best.date, NaiveDate::from_ymd(2026, 1, 15));
+
+        let imperial = vec![day(15, vec![at(10.0, units::fahrenheit(19.0), Some(units::mph(10.0))), at(0.0, units::fahrenheit(21.0), Some(units::mph(10.0)))])];
+        let imperial_best = best_day(&imperial, &BestDayWeights::default(), true).unwrap();
+        assert!((imperial_best.score - best.score).abs() < 0.001);
+        assert!(best_day(&data[2..], &BestDayWeights::default(), false).is_none());
+    }
+
+    #[test]
+    fn clothing_advice_covers_cold_rain_wind_and_snow() {
+        let at = |status: WeatherStatus, feels_like: f32, precipitation: f32, wind_gust: f32| Forecast {
+            status, feels_like: Some(feels_like), precipitation: Some(precipitation), wind_gust: Some(wind_gust), ..Forecast::default()
+        };
+
+        let advice = clothing_advice(&day(14, vec![at(WeatherStatus::LightRain, 5.0, 70.0, 50.0), at(WeatherStatus::LightSnow, 7.0, 20.0, 10.0)]), false).unwrap();
+        assert_eq!(advice.items, [Clothing::Waterproof, Clothing::WarmCoat, Clothing::Boots]);
+        assert_eq!(advice.text, "Take a waterproof and a warm coat. Wear waterproof boots.");
+
+        let advice = clothing_advice(&day(14, vec![at(WeatherStatus::SunnyDay, 25.0, 10.0, 45.0)]), false).unwrap();
+        assert_eq!((advice.items, advice.text), (vec![Clothing::Windproof], "Take something windproof.".to_string()));
+
+        let advice = clothing_advice(&day(14, vec![at(WeatherStatus::Cloudy, 23.0, 0.0, 10.0)]), true).unwrap();
+        assert_eq!(advice.items, [Clothing::HeavyCoat]);
+        assert!(clothing_advice(&day(14, vec![Forecast::default()]), false).is_none());
+    }
+}
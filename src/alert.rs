@@ -0,0 +1,199 @@
+use std::{str::FromStr, fmt, path::Path};
+use anyhow::{Context, Result, anyhow};
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use chrono::{NaiveDate, NaiveTime, DateTime, Utc, Local, Duration};
+use crate::raw::{Location, Forecast};
+use crate::DayWrapper;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Above,
+    Below
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertCondition {
+    field: String,
+    comparison: Comparison,
+    threshold: f32
+}
+
+const FIELDS: &[&str] = &["precipitation", "temperature", "feels_like", "wind_speed", "wind_gust", "visibility", "humidity", "uv_index", "snow_probability"];
+
+impl FromStr for AlertCondition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let fmt_regex = regex::Regex::new(r"^\s*([a-z_]+)\s*([<>])\s*(-?[0-9]+(?:\.[0-9]+)?)\s*$")?;
+        let caps = fmt_regex.captures(s).context("see --help for correct format")?;
+        let field = caps.get(1).context("regex error")?.as_str().to_string();
+        if !FIELDS.contains(&field.as_str()) {
+            return Err(anyhow!("unknown field, expected one of {}", FIELDS.join(", ")))
+        }
+
+        let comparison = match caps.get(2).context("regex error")?.as_str() {
+            ">" => Comparison::Above,
+            _ => Comparison::Below
+        };
+        let threshold = caps.get(3).context("regex error")?.as_str().parse()?;
+        Ok(AlertCondition { field, comparison, threshold })
+    }
+}
+
+impl fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let comparison = match self.comparison {
+            Comparison::Above => ">",
+            Comparison::Below => "<"
+        };
+        write!(f, "{} {} {}", self.field, comparison, self.threshold)
+    }
+}
+
+impl AlertCondition {
+    fn value(&self, forecast: &Forecast) -> Option<f32> {
+        match self.field.as_str() {
+            "precipitation" => forecast.precipitation,
+            "temperature" => forecast.temperature,
+            "feels_like" => forecast.feels_like,
+            "wind_speed" => forecast.wind_speed,
+            "wind_gust" => forecast.wind_gust,
+            "visibility" => forecast.visibility,
+            "humidity" => forecast.humidity,
+            "snow_probability" => forecast.snow_probability,
+            _ => forecast.uv_index
+        }
+    }
+
+    fn is_met(&self, value: f32) -> bool {
+        match self.comparison {
+            Comparison::Above => value > self.threshold,
+            Comparison::Below => value < self.threshold
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Alert {
+    pub condition: String,
+    pub date: NaiveDate,
+    pub time: NaiveTime,
+    pub value: f32,
+    #[serde(skip)]
+    #[schemars(skip)]
+    comparison: Comparison
+}
+
+impl Alert {
+    /// Whether the forecast value is further past the threshold than a previous one.
+    fn is_worse_than(&self, previous: f32) -> bool {
+        match self.comparison {
+            Comparison::Above => self.value > previous,
+            Comparison::Below => self.value < previous
+        }
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {} on {} (forecast {:.1})", self.condition, self.time.format("%H:%M"), self.date.format("%a %e %B"), self.value)
+    }
+}
+
+/// Finds the first forecast time at which each condition is met.
+pub fn check(conditions: &[AlertCondition], data: &[DayWrapper]) -> Vec<Alert> {
+    conditions.iter().filter_map(|condition| {
+        data.iter()
+            .flat_map(|day| day.times.iter().map(move |time| (day.date, time)))
+            .filter_map(|(date, time)| Some((date, time.time, condition.value(&time.forecast)?)))
+            .find(|(_, _, value)| condition.is_met(*value))
+            .map(|(date, time, value)| Alert { condition: condition.to_string(), date, time, value, comparison: condition.comparison })
+    }).collect()
+}
+
+/// A notification that was raised, so the same event isn't notified on every run.
+#[derive(Debug, Serialize, Deserialize)]
+struct Notified {
+    location: String,
+    condition: String,
+    date: NaiveDate,
+    time: NaiveTime,
+    value: f32,
+    notified_at: DateTime<Utc>
+}
+
+/// Leaves out alerts that have already been notified for the same location, condition and forecast
+/// time, unless the forecast has worsened since and it has been long enough, and remembers the rest.
+/// The notifications are kept in the cache directory until the forecast times have passed. If they
+/// can't be, every alert is notified rather than none.
+pub fn unnotified(dir: Option<&Path>, location: &Location, alerts: Vec<Alert>, renotify_after: Duration) -> Vec<Alert> {
+    let Some(dir) = dir else {
+        eprintln!("Warning: can't find cache directory to remember notified alerts in");
+        return alerts
+    };
+    let path = dir.join("alerts.json");
+    let mut notified = match load_notified(&path) {
+        Ok(notified) => notified,
+        Err(err) => {
+            eprintln!("Warning: forgetting notified alerts: {:#}", err);
+            Vec::new()
+        }
+    };
+
+    let now = Utc::now();
+    let key = location.geohash.clone().unwrap_or_else(|| location.name.clone());
+    let today = Local::today().naive_local();
+    notified.retain(|n| n.date >= today);
+
+    let mut unnotified = Vec::new();
+    for alert in alerts {
+        let previous = notified.iter_mut().find(|n| n.location == key && n.condition == alert.condition && n.date == alert.date && n.time == alert.time);
+        match previous {
+            Some(previous) if !alert.is_worse_than(previous.value) || now - previous.notified_at < renotify_after => (),
+            Some(previous) => {
+                previous.value = alert.value;
+                previous.notified_at = now;
+                unnotified.push(alert);
+            },
+            None => {
+                notified.push(Notified { location: key.clone(), condition: alert.condition.clone(), date: alert.date, time: alert.time, value: alert.value, notified_at: now });
+                unnotified.push(alert);
+            }
+        }
+    }
+
+    if let Err(err) = save_notified(dir, &path, &notified) {
+        eprintln!("Warning: can't remember notified alerts: {:#}", err);
+    }
+    unnotified
+}
+
+fn load_notified(path: &Path) -> Result<Vec<Notified>> {
+    if !path.exists() {
+        return Ok(Vec::new())
+    }
+    let file = std::fs::File::open(path).context("can't read alert notifications")?;
+    serde_json::from_reader(file).context("malformed alert notifications")
+}
+
+fn save_notified(dir: &Path, path: &Path, notified: &[Notified]) -> Result<()> {
+    std::fs::create_dir_all(dir).context("can't create cache directory")?;
+    let file = std::fs::File::create(path).context("can't write alert notifications")?;
+    serde_json::to_writer(file, notified)?;
+    Ok(())
+}
+
+pub fn notify(location: &Location, alerts: &[Alert]) {
+    for alert in alerts {
+        let summary = format!("Weather alert for {}", location.name);
+        let shown = notify_rust::Notification::new()
+            .summary(&summary)
+            .body(&alert.to_string())
+            .show();
+
+        if shown.is_err() {
+            eprintln!("{}: {}", summary, alert);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Serialize, Deserialize};
+use chrono::{DateTime, Utc, Duration};
+
+#[derive(Debug, Clone, Copy)]
+pub enum CachePolicy {
+    Refresh,
+    MaxAge(Duration),
+    Offline
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Entry {
+    pub fetched_at: DateTime<Utc>,
+    pub body: String,
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>
+}
+
+/// The platform's cache directory for this program, if there is one.
+pub fn default_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "weather").map(|dirs| dirs.cache_dir().to_path_buf())
+}
+
+fn entry_path(dir: Option<&Path>, key: &str) -> Result<PathBuf> {
+    let dir = dir.context("can't find cache directory")?;
+    Ok(dir.join(format!("{}.json", urlencoding::encode(key))))
+}
+
+fn previous_path(dir: Option<&Path>, key: &str) -> Result<PathBuf> {
+    Ok(entry_path(dir, key)?.with_extension("previous.json"))
+}
+
+fn load_path(path: PathBuf) -> Result<Option<Entry>> {
+    if !path.exists() {
+        return Ok(None)
+    }
+
+    let file = std::fs::File::open(path).context("can't read cache entry")?;
+    Ok(Some(serde_json::from_reader(file).context("malformed cache entry")?))
+}
+
+/// Loads an entry, if there is one. Without a cache directory nothing is ever cached.
+pub fn load(dir: Option<&Path>, key: &str) -> Result<Option<Entry>> {
+    if dir.is_none() {
+        return Ok(None)
+    }
+    load_path(entry_path(dir, key)?)
+}
+
+/// Loads the entry that was replaced by the most recent store, if any.
+pub fn load_previous(dir: Option<&Path>, key: &str) -> Result<Option<Entry>> {
+    if dir.is_none() {
+        return Ok(None)
+    }
+    load_path(previous_path(dir, key)?)
+}
+
+/// Writes an entry to a temporary file beside it, which is then renamed into place, so that an
+/// interrupted write never leaves a partial entry behind.
+fn write(path: &Path, entry: &Entry) -> Result<PathBuf> {
+    let temp = path.with_extension(format!("{}.tmp", std::process::id()));
+    let file = std::fs::File::create(&temp).context("can't write cache entry")?;
+    if let Err(err) = serde_json::to_writer(file, entry) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(err).context("can't write cache entry")
+    }
+    Ok(temp)
+}
+
+fn replace(temp: PathBuf, path: &Path) -> Result<()> {
+    std::fs::rename(temp, path).context("can't replace cache entry")
+}
+
+/// Stores an entry, keeping the one it replaces as the previous entry.
+pub fn store(dir: Option<&Path>, key: &str, entry: &Entry) -> Result<()> {
+    let path = entry_path(dir, key)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("can't create cache directory")?;
+    }
+
+    let temp = write(&path, entry)?;
+    if path.exists() {
+        std::fs::rename(&path, previous_path(dir, key)?).context("can't replace cache entry")?;
+    }
+    replace(temp, &path)
+}
+
+/// Saves an entry without keeping the one it replaces, for when the server reports it is unchanged.
+pub fn refresh(dir: Option<&Path>, key: &str, entry: &Entry) -> Result<()> {
+    let path = entry_path(dir, key)?;
+    replace(write(&path, entry)?, &path)
+}
+
+/// The start of the name of every kind of cache entry.
+const KEY_PREFIXES: [&str; 5] = ["forecast-", "search-", "nearest-", "open-meteo-", "what3words-"];
+
+fn is_entry_file(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else { return false };
+    // Temporary files are only left behind by writes that were interrupted.
+    (name.ends_with(".json") || name.ends_with(".tmp")) && KEY_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// Removes every cache entry in the directory, returning how many were removed. Only entry
+/// files are removed, in case the directory is shared with other files.
+pub fn clear(dir: Option<&Path>) -> Result<usize> {
+    let dir = dir.context("can't find cache directory")?;
+    if !dir.exists() {
+        return Ok(0)
+    }
+
+    let mut removed = 0;
+    for file in std::fs::read_dir(dir).context("can't read cache directory")? {
+        let path = file?.path();
+        if path.is_file() && is_entry_file(&path) {
+            std::fs::remove_file(&path).context("can't remove cache entry")?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("weather-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn clear_leaves_other_files() {
+        let dir = temp_dir("clear");
+        let entry = |body: &str| Entry { fetched_at: Utc::now(), body: body.to_string(), etag: None, last_modified: None };
+        store(Some(&dir), "forecast-gcpvj0v07", &entry("first")).unwrap();
+        store(Some(&dir), "forecast-gcpvj0v07", &entry("second")).unwrap();
+        store(Some(&dir), "search-london-", &entry("[]")).unwrap();
+        std::fs::write(dir.join("alerts.json"), "[]").unwrap();
+        std::fs::write(dir.join("settings.json"), "{}").unwrap();
+
+        assert_eq!(clear(Some(&dir)).unwrap(), 3);
+        assert!(dir.join("alerts.json").exists());
+        assert!(dir.join("settings.json").exists());
+        assert!(load(Some(&dir), "forecast-gcpvj0v07").unwrap().is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn store_keeps_the_previous_entry_and_no_temporary_files() {
+        let dir = temp_dir("store");
+        let entry = |body: &str| Entry { fetched_at: Utc::now(), body: body.to_string(), etag: None, last_modified: None };
+        store(Some(&dir), "forecast-gcpvj0v07", &entry("first")).unwrap();
+        store(Some(&dir), "forecast-gcpvj0v07", &entry("second")).unwrap();
+        refresh(Some(&dir), "forecast-gcpvj0v07", &entry("third")).unwrap();
+
+        assert_eq!(load(Some(&dir), "forecast-gcpvj0v07").unwrap().unwrap().body, "third");
+        assert_eq!(load_previous(Some(&dir), "forecast-gcpvj0v07").unwrap().unwrap().body, "first");
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
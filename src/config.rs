@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use crate::advice::{BestDayWeights, GustThresholds};
+use crate::SpinnerStyle;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Config {
+    pub base_url: Option<String>,
+    pub cache_dir: Option<PathBuf>,
+    pub what3words_key: Option<String>,
+    pub best_day: BestDayWeights,
+    pub gusts: GustThresholds,
+    pub status_threshold: Option<f32>,
+    pub spinner: SpinnerConfig,
+    pub selectors: Selectors
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SpinnerConfig {
+    pub style: Option<SpinnerStyle>,
+    pub ticks: Option<String>,
+    pub template: Option<String>
+}
+
+/// Overrides for the CSS selectors used to scrape Met Office forecast pages, for when the
+/// page layout changes. Each is the selector for the elements holding that field's values.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Selectors {
+    pub day: Option<String>,
+    pub time: Option<String>,
+    pub status: Option<String>,
+    pub precipitation: Option<String>,
+    pub temperature: Option<String>,
+    pub feels_like: Option<String>,
+    pub wind_speed: Option<String>,
+    pub wind_direction: Option<String>,
+    pub wind_gust: Option<String>,
+    pub visibility: Option<String>,
+    pub humidity: Option<String>,
+    pub uv_index: Option<String>,
+    pub pressure: Option<String>,
+    pub snow_amount: Option<String>,
+    pub tide: Option<String>,
+    pub tide_cell: Option<String>,
+    pub sea_temperature: Option<String>
+}
+
+pub fn load() -> Result<Config> {
+    let Some(dirs) = directories::ProjectDirs::from("", "", "weather") else {
+        return Ok(Config::default())
+    };
+
+    let path = dirs.config_dir().join("config.toml");
+    if !path.exists() {
+        return Ok(Config::default())
+    }
+
+    let text = std::fs::read_to_string(path).context("can't read config file")?;
+    toml::from_str(&text).context("malformed config file")
+}
@@ -0,0 +1,157 @@
+use std::path::Path;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use schemars::JsonSchema;
+use crate::raw::Location;
+use crate::DayWrapper;
+
+const FORECAST_COLUMNS: &str = "
+    geohash TEXT NOT NULL,
+    date TEXT NOT NULL,
+    time TEXT NOT NULL,
+    fetched_at TEXT NOT NULL,
+    location TEXT NOT NULL,
+    units TEXT NOT NULL,
+    status TEXT NOT NULL,
+    precipitation REAL,
+    temperature REAL,
+    feels_like REAL,
+    wind_speed REAL,
+    wind_direction TEXT,
+    wind_gust REAL,
+    visibility REAL,
+    humidity REAL,
+    uv_index REAL,
+    pressure REAL,
+    PRIMARY KEY (geohash, date, time, fetched_at)
+";
+
+fn open(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path).context("can't open sqlite database")?;
+    conn.execute_batch(&format!("CREATE TABLE IF NOT EXISTS forecasts ({});", FORECAST_COLUMNS))
+        .context("can't create forecasts table")?;
+
+    let has_pressure = conn.prepare("SELECT 1 FROM pragma_table_info('forecasts') WHERE name = 'pressure'")?.exists([])?;
+    if !has_pressure {
+        conn.execute_batch("ALTER TABLE forecasts ADD COLUMN pressure REAL;").context("can't update forecasts table")?;
+    }
+
+    // Older databases required every field, from before missing values were kept as NULL.
+    let not_null = conn.prepare("SELECT 1 FROM pragma_table_info('forecasts') WHERE name = 'temperature' AND \"notnull\" = 1")?.exists([])?;
+    if not_null {
+        conn.execute_batch(&format!("
+            BEGIN;
+            CREATE TABLE forecasts_new ({});
+            INSERT INTO forecasts_new SELECT
+                geohash, date, time, fetched_at, location, units, status, precipitation, temperature,
+                feels_like, wind_speed, wind_direction, wind_gust, visibility, humidity, uv_index, pressure
+            FROM forecasts;
+            DROP TABLE forecasts;
+            ALTER TABLE forecasts_new RENAME TO forecasts;
+            COMMIT;
+        ", FORECAST_COLUMNS)).context("can't update forecasts table")?;
+    }
+
+    Ok(conn)
+}
+
+pub fn record(path: &Path, location: &Location, fetched_at: DateTime<Utc>, freedom_units: bool, data: &[DayWrapper]) -> Result<()> {
+    let geohash = location.geohash.as_deref().context("can't record a location without a geohash")?;
+    let units = if freedom_units { "us" } else { "metric" };
+
+    let mut conn = open(path)?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare("
+            INSERT OR REPLACE INTO forecasts (
+                geohash, date, time, fetched_at, location, units, status, precipitation, temperature,
+                feels_like, wind_speed, wind_direction, wind_gust, visibility, humidity, uv_index, pressure
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+        ")?;
+
+        for day in data {
+            // The current time marker is interpolated for whenever the forecast was asked for, so isn't a forecast time.
+            for time in day.times.iter().filter(|time| !time.now) {
+                let f = &time.forecast;
+                stmt.execute(params![
+                    geohash, day.date.to_string(), time.time.format("%H:%M").to_string(), fetched_at.to_rfc3339(),
+                    location.name, units, f.status.label(), f.precipitation, f.temperature, f.feels_like,
+                    f.wind_speed, f.wind_direction, f.wind_gust, f.visibility, f.humidity, f.uv_index, f.pressure
+                ])?;
+            }
+        }
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Accuracy {
+    pub compared: usize,
+    pub mean_temperature_error: Option<f32>,
+    pub precipitation_hit_rate: Option<f32>
+}
+
+pub fn accuracy(path: &Path, geohash: &str, freedom_units: bool) -> Result<Accuracy> {
+    let units = if freedom_units { "us" } else { "metric" };
+
+    let conn = open(path)?;
+    let mut stmt = conn.prepare("
+        WITH actual AS (
+            SELECT date, time, temperature, precipitation, MAX(fetched_at) AS fetched_at
+            FROM forecasts WHERE geohash = ?1 AND units = ?2 AND temperature IS NOT NULL AND precipitation IS NOT NULL
+            GROUP BY date, time
+        )
+        SELECT f.temperature - a.temperature, f.precipitation >= 50.0, a.precipitation >= 50.0
+        FROM forecasts f JOIN actual a ON f.date = a.date AND f.time = a.time
+        WHERE f.geohash = ?1 AND f.units = ?2 AND f.fetched_at < a.fetched_at
+            AND f.temperature IS NOT NULL AND f.precipitation IS NOT NULL
+    ")?;
+
+    let rows = stmt.query_map(params![geohash, units], |row| {
+        Ok((row.get::<_, f32>(0)?, row.get::<_, bool>(1)?, row.get::<_, bool>(2)?))
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    if rows.is_empty() {
+        return Ok(Accuracy { compared: 0, mean_temperature_error: None, precipitation_hit_rate: None })
+    }
+
+    let compared = rows.len();
+    let temperature_error = rows.iter().map(|(error, _, _)| error.abs()).sum::<f32>() / compared as f32;
+    let hits = rows.iter().filter(|(_, predicted, actual)| predicted == actual).count();
+
+    Ok(Accuracy {
+        compared,
+        mean_temperature_error: Some(temperature_error),
+        precipitation_hit_rate: Some(hits as f32 / compared as f32)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, NaiveTime};
+    use crate::raw::Forecast;
+    use crate::TimeWrapper;
+
+    #[test]
+    fn records_forecast_times_but_not_the_current_time() {
+        let path = std::env::temp_dir().join(format!("weather-history-{}.sqlite", std::process::id()));
+        let location = Location { name: "London".to_string(), area: None, geohash: Some("gcpvj0v07".to_string()), latitude: None, longitude: None };
+        let at = |hour: u32, minute: u32| TimeWrapper::new(NaiveTime::from_hms(hour, minute, 0), Forecast::default());
+        let now = TimeWrapper { now: true, ..at(10, 42) };
+        let day = DayWrapper {
+            date: NaiveDate::from_ymd(2026, 1, 14), summary: None, daylight: None, tides: Vec::new(), sea_temperature: None,
+            pressure_trend: None, sun_advice: Vec::new(), advice: None, snow: None, wet_hours: 0, times: vec![at(9, 0), now, at(12, 0)]
+        };
+
+        record(&path, &location, Utc::now(), false, &[day]).unwrap();
+        let times: Vec<String> = open(&path).unwrap().prepare("SELECT time FROM forecasts ORDER BY time").unwrap()
+            .query_map([], |row| row.get(0)).unwrap().collect::<Result<_, _>>().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(times, ["09:00", "12:00"]);
+    }
+}
@@ -1,14 +1,29 @@
-#![feature(let_else, backtrace)]
-
 mod raw;
+mod cache;
+mod sun;
+mod history;
+mod alert;
+mod config;
+mod status;
+mod advice;
+mod openmeteo;
+mod picker;
+mod what3words;
+mod template;
+mod units;
 
-use std::{str::FromStr};
-use serde::Serialize;
-use clap::Parser;
+use std::{str::FromStr, path::PathBuf};
+use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
+use clap::{Parser, ArgEnum};
 use anyhow::{Context, Result, anyhow};
 use comfy_table::{Table, Row, Cell};
-use chrono::{NaiveDate, NaiveTime};
-use raw::{Location, Forecast};
+use chrono::{NaiveDate, NaiveTime, NaiveDateTime, DateTime, Utc, FixedOffset, Local, Duration, Timelike, Datelike, Weekday};
+use cache::CachePolicy;
+use alert::{Alert, AlertCondition};
+use advice::{SunAdvice, BestDay, ClothingAdvice, GustSeverity};
+use raw::{Location, Forecast, Tide, TideKind, LocationFilter};
+use status::{WeatherStatus, Condition};
 use indicatif::{ProgressBar, ProgressStyle};
 
 #[derive(Parser, Debug, Clone)]
@@ -16,16 +31,16 @@ use indicatif::{ProgressBar, ProgressStyle};
 struct Args {
     #[clap(
         help = "Location to forecast. Blank means current location",
-        long_help = "The location you want to find a forecast for. If you leave this blank, the app will attempt to find your current location. If the location you enter is ambiguous and non-interactive mode is not enabled, you will be asked to pick a preferred location."
+        long_help = "The location you want to find a forecast for. If you leave this blank, the location in the WEATHER_DEFAULT_LOCATION environment variable is used, or if that isn't set, the app will attempt to find your current location. You can also enter a latitude and longitude separated by a comma, or a what3words address such as ///index.home.raft, in which case the nearest named location is used. what3words addresses need an API key, set in the WHAT3WORDS_API_KEY environment variable or as what3words_key in the config file. If the location you enter is ambiguous and non-interactive mode is not enabled, you will be asked to pick a preferred location. Similarly, if it is too broad to forecast, such as a county, you will be asked to pick a place within it, or in non-interactive mode some are suggested."
     )]
     location: Option<String>,
 
     #[clap(
-        short, long, default_value = "0", 
+        short, long, default_value = "0", parse(try_from_str),
         help = "Day to start forecasting, relative to today",
-        long_help = "The number of days in the future to start the forecast from. This must be positive - zero is today, one is tomorrow, etc. This is provided on a best-effort basis, most locations have only a few days of forecasts available."
+        long_help = "The number of days in the future to start the forecast from. This must be positive - zero is today, one is tomorrow, etc. You can also give the name of a weekday, such as friday or fri, for its next occurrence, which is today if today is that day. This is provided on a best-effort basis, most locations have only a few days of forecasts available."
     )]
-    day: usize,
+    day: Day,
 
     #[clap(
         short, long, default_value = "1",
@@ -34,13 +49,55 @@ struct Args {
     )]
     count: usize,
 
+    #[clap(
+        long = "2day", conflicts_with_all = &["day", "count", "week", "next"],
+        help = "Forecast today and tomorrow",
+        long_help = "A shortcut for --day 0 --count 2, forecasting today and tomorrow."
+    )]
+    two_day: bool,
+
+    #[clap(
+        long, conflicts_with_all = &["count", "next"],
+        help = "Forecast a week",
+        long_help = "A shortcut for --count 7, forecasting seven days from the start date. Most locations have fewer days of forecasts available, in which case as many as possible are shown."
+    )]
+    week: bool,
+
     #[clap(
         short, long, default_value = "0:3:8", parse(try_from_str),
         help = "Time range to forecast",
-        long_help = "The range of times that you want a forecast for on each day. This should be entered in the format start:step:count, where each field is an hour number 0-24 in local time. The default corresponds to the times 0:00, 4:00, 8:00, 12:00, 16:00, 20:00. This is also provided on a best effort basis - only forecasts in the future can be shown."
+        long_help = "The range of times that you want a forecast for on each day. This should be entered in the format start:step:count, where each field is an hour number 0-24 in local time, or as a comma separated list of hours such as 7,8,17,18. The default corresponds to the times 0:00, 3:00, 6:00, 9:00, 12:00, 15:00, 18:00, 21:00. This is also provided on a best effort basis - only forecasts in the future can be shown."
     )]
     time_range: TimeRange,
 
+    #[clap(
+        long, arg_enum, conflicts_with = "time-range",
+        help = "Use a named preset time range",
+        long_help = "Use a named set of times instead of a start:step:count time range. morning is every hour from 6:00 to 12:00, daytime is every two hours from 8:00 to 20:00, commute is 7:00, 8:00, 17:00 and 18:00, overnight is every two hours from 0:00 to 6:00, and hourly is every hour of the day."
+    )]
+    preset: Option<Preset>,
+
+    #[clap(
+        long, value_name = "HOURS", conflicts_with_all = &["day", "count", "time-range", "preset"],
+        help = "Forecast the next few hours",
+        long_help = "Forecast every hour for this many hours from the current hour, instead of a range of days and times. This continues into the following days as needed, with each day shown separately. Hours without forecast data are left out."
+    )]
+    next: Option<usize>,
+
+    #[clap(
+        long, value_name = "HOURS", conflicts_with = "next",
+        help = "Also forecast the hours around each time",
+        long_help = "Also forecast every hour up to this many hours either side of each requested time. Each group of times around a requested time is shown separately in the table."
+    )]
+    around: Option<u32>,
+
+    #[clap(
+        long, value_name = "PCT",
+        help = "Only show times likely to be wet",
+        long_help = "Only show the times with at least this percentage chance of precipitation, in both text and JSON output. Days without any such times are noted as dry all day. Daily summaries and advice are still worked out from the whole day."
+    )]
+    min_precip: Option<f32>,
+
     #[clap(
         short, long,
         help = "Enable JSON output",
@@ -55,6 +112,13 @@ struct Args {
     )]
     non_interactive: bool,
 
+    #[clap(
+        long, conflicts_with_all = &["location", "non-interactive"],
+        help = "Pick the location while typing its name",
+        long_help = "Instead of giving a location, type its name and pick it from a list of matching locations, which is updated as you type. Use the arrow keys to move through the list, enter to pick a location, and escape to give up. This is useful for places whose official names aren't obvious."
+    )]
+    interactive_search: bool,
+
     #[clap(
         short, long,
         help = "Output extra forecast data",
@@ -62,288 +126,3189 @@ struct Args {
     )]
     extra: bool,
 
+    #[clap(
+        long, arg_enum, use_delimiter = true, conflicts_with = "extra", value_name = "COLUMNS",
+        help = "Forecast data to output, in order",
+        long_help = "A comma separated list of the forecast data to output as rows of the table, in the given order, out of status, precip, temp, feels, wind, dir, gust, visibility, humidity, uv and pressure. This replaces the default rows and those added by --extra, for when only a few are wanted. The pressure row is still left out for forecasts without pressures. JSON output will contain all available data regardless of this flag."
+    )]
+    columns: Vec<Column>,
+
+    #[clap(
+        long, value_name = "N", parse(try_from_str = parse_max_columns),
+        help = "Most times to show in one table",
+        long_help = "The most times to show as columns of one table. A day with more times is split across several tables, one after another. By default this is however many fit in the width of the terminal, so that tables don't wrap in narrow terminals, and there is no limit when the output isn't a terminal."
+    )]
+    max_columns: Option<usize>,
+
+    #[clap(
+        long, conflicts_with_all = &["extra", "columns", "json-summary", "group-weeks"],
+        help = "Output only the wind",
+        long_help = "Output only the wind speed, direction and gusts at each time, for activities such as sailing and kite flying. The direction is also shown as an arrow pointing the way the wind is blowing, unless ASCII mode is enabled. In JSON output, each time has only its wind fields and gust severity."
+    )]
+    wind: bool,
+
     #[clap(
         short, long,
         help = "Use US customary units instead of metric",
-        long_help = "Switch the unit system to use for data output to US customary units (degrees Fahrenheit and miles per hour), instead of metric units (degrees Celsius and kilometres per hour)."
+        long_help = "Switch the unit system to use for data output to US customary units (degrees Fahrenheit, miles per hour and miles of visibility), instead of metric units (degrees Celsius, kilometres per hour and metres of visibility). Pressures are then in inches of mercury unless --pressure-unit is given."
     )]
     freedom_units: bool,
 
+    #[clap(
+        long,
+        help = "Show temperatures and speeds in both unit systems",
+        long_help = "Show temperatures and wind speeds in both metric and US customary units, such as 14.2C / 57.6f, with the unit system chosen by --freedom-units first. In JSON output, each forecast time gains an other_units field with the temperatures and speeds in the other unit system."
+    )]
+    both_units: bool,
+
     #[clap(
         short, long,
         help = "Disable UTF8 and color output",
         long_help = "Disable all UTF8 and colored outputs - all outputs will use plain ASCII. Furthermore, if non-interactive mode is enabled, no escape codes will be used. The following abbreviations will be used for weather status: CL = Cloudy, SH = Showers, PC = Partly Cloudy, SU = Sunny, CN = Clear Night, SN = Snow, RA = Rain, SL = Sleet, TH = Thunderstorm."
     )]
-    ascii: bool
-}
+    ascii: bool,
 
-#[derive(Debug, Clone)]
-struct TimeRange {
-    start: usize,
-    step: usize,
-    count: usize
-}
+    #[clap(
+        long,
+        help = "Disable emoji but keep UTF8 and color",
+        long_help = "Use the two-letter weather status abbreviations listed under --ascii instead of emoji, and a non-emoji spinner, while keeping UTF8 table borders and colors. This is the default on consoles that are unlikely to be able to display emoji, such as the Linux console and the Windows console outside Windows Terminal."
+    )]
+    no_emoji: bool,
 
-impl FromStr for TimeRange {
-    type Err = anyhow::Error;
+    #[clap(
+        long,
+        help = "Ignore WEATHER_DEFAULT_LOCATION",
+        long_help = "Ignore the WEATHER_DEFAULT_LOCATION environment variable, so that your current location is used when no location is given."
+    )]
+    no_default: bool,
 
-    fn from_str(s: &str) -> Result<Self> {
-        let fmt_regex = regex::Regex::new("^(0?[0-9]|1[0-9]|2[0-3]):(0?[0-9]|1[0-9]|2[0-4]):(0?[0-9]|1[0-9]|2[0-4])$")?;
-        let caps = fmt_regex.captures(s).context("see --help for correct format")?;
-        let start = caps.get(1).context("regex error")?.as_str().parse()?;
-        let step = caps.get(2).context("regex error")?.as_str().parse()?;
-        let count = caps.get(3).context("regex error")?.as_str().parse()?;
-        if count*(step - 1) + start >= 24 {
-            Err(anyhow!("this time range overlaps the next day"))
-        } else {
-            Ok(TimeRange { start, step, count })
-        }
-    }
-}
+    #[clap(
+        long, arg_enum, use_delimiter = true, default_value = "metoffice", value_name = "BACKENDS",
+        help = "Forecast providers to try, in order",
+        long_help = "A comma separated list of forecast providers to try in order, out of metoffice and open-meteo. If getting a forecast from one fails, for example because the Met Office changed its pages or is unavailable, the next is tried using the coordinates of the location. Locations are always found using the Met Office. The provider that gave the forecast is reported if it wasn't the first, and given in the meta field of JSON output. The Open-Meteo URL can be overridden with the OPEN_METEO_BASE_URL environment variable."
+    )]
+    backend: Vec<Backend>,
 
-struct Mixer {
-    data: Vec<(NaiveTime, Forecast)>
-}
+    #[clap(
+        long, value_name = "HOURS",
+        help = "Summarise the next few hours in words",
+        long_help = "Output a sentence summarising this many hours from now, such as \"Next 12 hours: high 16.0C, low 9.5C, rain likely 14:00-16:00\", giving the highest and lowest temperature, when rain is likely (a precipitation chance of 50% or more) and any severe gusts. In JSON output, the summary is added as the summary_text field."
+    )]
+    hours_ahead: Option<u32>,
 
-impl Mixer {
-    fn new(mut data: Vec<(NaiveTime, Forecast)>) -> Mixer {
-        data.sort_by_key(|(time, _)| *time);
-        Mixer { data }
-    }
+    #[clap(
+        long,
+        help = "Show precipitation chances as bars",
+        long_help = "Add a row below the precipitation row showing each chance of precipitation as a bar, from empty at 0% to full at 100%, colored from green for unlikely to blue for likely. In ASCII mode the bars are made of # characters and are not colored."
+    )]
+    precip_bars: bool,
 
-    fn lerp(&self, time: NaiveTime) -> Option<Forecast> {
-        match self.data.binary_search_by_key(&time, |(time, _)| *time) {
-            Err(idx) if idx == 0 || idx == self.data.len() => None,
-            Ok(idx) => Some(self.data[idx].1.clone()),
-            Err(idx) => {
-                let (atime, afore) = self.data[idx - 1].clone();
-                let (btime, bfore) = self.data[idx].clone();
-                let t = (time - atime).num_minutes() as f32 / (btime - atime).num_minutes() as f32;
+    #[clap(
+        long,
+        help = "Show a strip of status icons below the times",
+        long_help = "Add a row just below the times showing only each time's status icon, in bold and colored by the kind of weather, such as yellow for sunny and blue for rain, so that dry or sunny spells stand out in wide tables. In ASCII mode the icons are the status codes and are not colored."
+    )]
+    summary_icons: bool,
 
-                Some(Forecast {
-                    status: if bfore.precipitation > afore.precipitation { bfore.status } else { afore.status },
-                    precipitation: (1.0 - t)*afore.precipitation + t*bfore.precipitation,
-                    temperature: (1.0 - t)*afore.temperature + t*bfore.precipitation,
-                    feels_like: (1.0 - t)*afore.feels_like + t * bfore.feels_like,
-                    wind_speed: (1.0 - t)*afore.wind_speed + t * bfore.wind_speed,
-                    wind_direction: afore.wind_direction,
-                    wind_gust: (1.0 - t)*afore.wind_gust + t * bfore.wind_gust,
-                    visibility: (1.0 - t)*afore.visibility + t * bfore.visibility,
-                    humidity: (1.0 - t)*afore.humidity + t * bfore.humidity,
-                    uv_index: afore.uv_index.max(bfore.uv_index)
-                })
-            }
-        }
-        
-    }
-}
+    #[clap(
+        long,
+        help = "Fail if any forecast field can't be read",
+        long_help = "Fail with an error naming the field if any forecast field can't be read from the Met Office page for every forecast time, rather than leaving it missing. This is useful when ingesting forecasts into a data pipeline, so that defaulted values are never recorded."
+    )]
+    strict: bool,
 
-#[derive(Debug, Serialize)]
-#[serde(untagged)]
-enum Output {
-    Data { location: Location, data: Vec<DayWrapper> },
-    Error { error: serde_error::Error }
-}
+    #[clap(
+        short, long,
+        help = "Only use cached data",
+        long_help = "Enable offline mode. Locations and forecasts will be read exclusively from the cache of previous results, and the network will never be used. If there is no cached data for the location, an error is reported. The time the cached forecast was fetched is shown alongside the output."
+    )]
+    offline: bool,
 
-#[derive(Debug, Serialize)]
-struct DayWrapper {
-    date: NaiveDate,
-    times: Vec<TimeWrapper>
-}
+    #[clap(
+        long, default_value = "30", value_name = "DAYS",
+        help = "Days to reuse cached location searches for",
+        long_help = "The number of days that the results of searching for a location are reused from the cache for, without asking the Met Office again. Locations rarely change, so this is much longer than forecasts are kept for. Give 0 to always search again."
+    )]
+    location_cache_days: i64,
 
-#[derive(Debug, Serialize)]
-struct TimeWrapper {
-    time: NaiveTime,
-    forecast: Forecast
-}
+    #[clap(
+        long, default_value = "60", value_name = "MINUTES",
+        help = "Warn about forecasts older than this",
+        long_help = "Warn when the forecast shown was fetched more than this many minutes ago, which can happen in offline mode or when serving, so that an outdated forecast isn't mistaken for a current one. In JSON output, such forecasts are marked with a stale field."
+    )]
+    stale_after: i64,
 
-fn cli_main(args: Args) -> Result<(Location, Vec<DayWrapper>)> {
-    let spinner_style = ProgressStyle::default_spinner()
-        .tick_chars(if args.ascii { "|/-\\" } else { "🌑🌒🌓🌔🌕🌖🌗🌘" })
-        .template("{prefix:.bold.dim} {spinner} {wide_msg}");
+    #[clap(
+        long, value_name = "PATH",
+        help = "Directory to cache results in",
+        long_help = "The directory to cache locations and forecasts in, instead of the usual cache directory for your platform, such as a tmpfs or a shared drive. It is created if needed. This can also be set with the cache_dir key of the config file."
+    )]
+    cache_dir: Option<PathBuf>,
 
-    let bar = ProgressBar::new_spinner();
-    if !args.non_interactive {
-        bar.set_style(spinner_style);
-        bar.set_message("Finding location");
-        bar.enable_steady_tick(100);
-    }
+    #[clap(
+        long,
+        help = "Remove all cached results and exit",
+        long_help = "Remove every cached location and forecast from the cache directory, then exit. Only the cache entries are removed, so any other files in a directory given with --cache-dir are left alone."
+    )]
+    clear_cache: bool,
 
-    let location = if let Some(location) = raw::get_location(args.location.clone(), args.non_interactive, args.ascii, bar.clone())? {
-        location
-    } else {
-        if !args.non_interactive {
-            bar.finish_and_clear();
-        }
+    #[clap(
+        short, long,
+        help = "Output a summary of each day",
+        long_help = "Output a one-line summary of each day above its forecast, giving the number of hours with a greater than 50% chance of precipitation, computed from the whole day rather than just the requested time range, after a short description of the weather at the requested times such as \"mostly cloudy with afternoon showers\". Falling pressure and severe gusts at the requested times are also noted. The gust thresholds can be set in the [gusts] section of the config file, with the keys amber and red in kilometres per hour. In JSON output, the summary is added as a field of each day."
+    )]
+    summary: bool,
 
-        return Err(anyhow!("That location could not be found: perhaps there is a typo, or your location services are off."))
-    };
+    #[clap(
+        short, long,
+        help = "Only search for beaches",
+        long_help = "Restrict the location search to beaches. Beach forecasts also include the times and heights of high and low tides, and the sea temperature, where available. This has no effect when forecasting for your current location."
+    )]
+    beaches: bool,
 
-    let geohash = if let Some(geohash) = location.geohash.clone() {
-        geohash
-    } else {
-        if !args.non_interactive {
-            bar.finish_and_clear();
-        }
+    #[clap(
+        long, value_name = "PERCENT",
+        help = "Precipitation chance that decides interpolated statuses",
+        long_help = "Choose the weather status of times between two forecasts from the nearer forecast, unless the other has a chance of precipitation of at least this percentage and the nearer one doesn't. Without this, the status of the forecast with the higher chance of precipitation is always used. This can also be set with the status_threshold key of the config file."
+    )]
+    status_threshold: Option<f32>,
 
-        return Err(anyhow!("That location is too broad, please pick a more specific location."))
-    };
+    #[clap(
+        long, conflicts_with = "status-threshold",
+        help = "Take interpolated statuses from the nearest forecast",
+        long_help = "Between two of the Met Office's forecasts, keep interpolating the temperatures, speeds and chances of precipitation, but take the weather status and wind direction from whichever forecast is nearer in time, rather than from the one with the higher chance of precipitation. This overrides the status_threshold key of the config file."
+    )]
+    nearest_status: bool,
 
-    if !args.non_interactive {
-        bar.set_message(format!("Getting forecast for {} ({})", location.name, location.area.as_deref().unwrap_or("N/A")));
-    }
+    #[clap(
+        long, value_name = "NAME",
+        help = "Prefer locations in this region",
+        long_help = "When a location is ambiguous, prefer the candidates whose area contains this name, such as a county or region. If exactly one candidate matches it is picked automatically, and otherwise only the matching candidates are offered. If none match, all candidates are offered as usual."
+    )]
+    region: Option<String>,
 
-    let data = raw::get_forecast(geohash, args.freedom_units)?;
+    #[clap(
+        long,
+        help = "Pick the best match for ambiguous locations",
+        long_help = "In non-interactive mode, pick the most relevant of several matching locations rather than rejecting the location as ambiguous. The location that was picked is noted on stderr. This has no effect in interactive mode, where you are asked to pick a location instead."
+    )]
+    pick_best: bool,
 
-    if !args.non_interactive {
-        bar.finish_and_clear();
-    }
+    #[clap(
+        long,
+        help = "Explain how the location was found",
+        long_help = "Note each step of finding the location on stderr: how the location was read, such as a postcode, coordinates or a place name, the term that was searched for, how many locations matched, and why the chosen location was picked, such as being the only or exact match, the nearest, or picked by you."
+    )]
+    explain_location: bool,
 
-    let mut odata = Vec::new();
-    for (date, fs) in data.into_iter().skip(args.day).take(args.count) {
-        let mixer = Mixer::new(fs);
-        let mut times = Vec::new();
-        let mut t = args.time_range.start;
-        for _ in 0..args.time_range.count {
-            let time = NaiveTime::from_hms(t as u32, 0, 0);
-            t += args.time_range.step;
-            let Some(forecast) = mixer.lerp(time) else { continue };
-            times.push(TimeWrapper { time, forecast });
-        }
-        odata.push(DayWrapper { date, times });
-    }
+    #[clap(
+        long, value_name = "N", default_value = "10",
+        help = "Most matching locations to offer",
+        long_help = "The number of locations to offer when a location is ambiguous or too broad and you are asked to pick one. The most relevant locations are offered first, and the rest are left out with a note to refine your search. Non-interactive and JSON output always include every matching location."
+    )]
+    max_results: usize,
 
-    Ok((location, odata))
-}
+    #[clap(
+        long,
+        help = "Output the hours where snow is likely",
+        long_help = "Output the hours of each day where snow is likely, along with the total expected snowfall where it is available, in centimetres or inches. The Met Office doesn't give a chance of snow, so it is estimated from the weather status and the temperature. In JSON output, this is added as a field of each day, and the estimated chance of snow is added to every forecast."
+    )]
+    snow: bool,
 
-fn format_output_failure(error: anyhow::Error) {
-    println!("Error: ");
-    for (i, err) in error.chain().enumerate() {
-        println!("  {}: {}", i, err);
-    }
+    #[clap(
+        long, conflicts_with = "json",
+        help = "Enable newline-delimited JSON output",
+        long_help = "Enable the newline-delimited JSON output mode. Each forecast time is output as a separate JSON object on its own line, containing the location, date, time and forecast fields. Errors are output in the same format as JSON output mode."
+    )]
+    ndjson: bool,
 
-    let bt = error.backtrace();
-    match bt.status() {
-        std::backtrace::BacktraceStatus::Captured => {
-            println!("\nBacktrace:\n{}", bt);
-        },
-        _ => println!("\nNo backtrace captured.")
-    }
-}
+    #[clap(
+        long, conflicts_with_all = &["json", "ndjson"],
+        help = "Enable flat JSON array output",
+        long_help = "Enable the flat JSON output mode. The forecast is output as a single JSON array with an object for each forecast time, containing the location, date, time and forecast fields, in the same form as newline-delimited JSON output. This is easier to load into tools such as jq or pandas than the nested JSON output. Errors are output in the same format as JSON output mode."
+    )]
+    flat_json: bool,
 
-fn format_output_success(args: Args, location: Location, data: Vec<DayWrapper>) {
-    println!("Forecast for {} ({})", location.name, location.area.as_deref().unwrap_or("N/A"));
+    #[clap(
+        long, conflicts_with_all = &["json", "ndjson", "flat-json", "prometheus", "env"],
+        help = "Enable CSV output",
+        long_help = "Enable the CSV output mode. Each forecast time is output as a row, after a header row, containing the location name and area, date, time and forecast fields. Errors are written to stderr."
+    )]
+    csv: bool,
 
-    let format_temp = |t: f32| if args.freedom_units {
-        format!("{:.1}f", t)
-    } else {
-        format!("{:.1}C", t)
-    };
+    #[clap(
+        long, value_name = "FILE", conflicts_with_all = &["json", "ndjson", "flat-json", "csv", "prometheus", "env"],
+        help = "Format the output with a template file",
+        long_help = "Output each day by filling in the template in this file, instead of as a table. Values are written as {{path}}, such as {{location.name}} or {{day.date}}, with {{path:1}} to give a number's decimal places. A section written as {{#path}}...{{/path}} is repeated for each item of a list, such as {{#day.times}}{{time}} {{forecast.temperature}}{{/day.times}}, with the item's values available inside it, and is shown once for other values, or left out if the value is missing. The values are the same as in JSON output, and missing values are written as -."
+    )]
+    template: Option<PathBuf>,
 
-    let format_speed = |t: f32| if args.freedom_units {
-        format!("{:.1}mph", t)
-    } else {
-        format!("{:.1}kph", t)
-    };
+    #[clap(
+        long, requires = "csv", value_name = "CHAR", parse(try_from_str = parse_delimiter),
+        help = "Delimiter for CSV output",
+        long_help = "The character to separate fields with in CSV output, instead of a comma. This is useful with spreadsheets in locales where the comma is the decimal separator, which usually expect a semicolon. Give tab for a tab."
+    )]
+    csv_delimiter: Option<char>,
 
-    if data.is_empty() {
-        println!("No applicable data available.");
-    }
+    #[clap(
+        long, requires = "csv",
+        help = "Make CSV output open cleanly in Excel",
+        long_help = "Start CSV output with a UTF8 byte order mark, so that Excel opens it with the right encoding rather than garbling the weather statuses and location names."
+    )]
+    excel: bool,
 
-    for DayWrapper { date, times: data } in data {
-        let mut table = Table::new();
-        let mut times = Row::new();
-        let mut status = Row::new();
-        let mut precip = Row::new();
-        let mut temp = Row::new();
-        let mut feels = Row::new();
-        let mut wind = Row::new();
-        let mut dir = Row::new();
-        let mut gust = Row::new();
-        let mut visib = Row::new();
-        let mut humid = Row::new();
-        let mut uv = Row::new();
+    #[clap(
+        long,
+        help = "Use a comma as the decimal separator",
+        long_help = "Write numbers in the table and CSV output with a comma rather than a full stop before the decimals, as is usual in much of Europe. CSV output then separates fields with a semicolon unless --csv-delimiter is given. JSON output is unaffected, since JSON numbers always use a full stop."
+    )]
+    decimal_comma: bool,
 
-        times.add_cell(Cell::new("Time"));
-        status.add_cell(Cell::new("Status"));
-        precip.add_cell(Cell::new("Precipitation"));
-        temp.add_cell(Cell::new("Temperature"));
-        feels.add_cell(Cell::new("Feels Like"));
-        wind.add_cell(Cell::new("Wind Speed"));
-        dir.add_cell(Cell::new("Wind Direction"));
-        gust.add_cell(Cell::new("Wind Gust"));
-        visib.add_cell(Cell::new("Visibility"));
-        humid.add_cell(Cell::new("Humidity"));
-        uv.add_cell(Cell::new("UV Index"));
+    #[clap(
+        long, value_name = "N", parse(try_from_str = parse_precision),
+        help = "Decimal places for temperatures and wind speeds",
+        long_help = "The number of decimal places, from 0 to 3, to show temperatures, feels like temperatures, wind speeds and gusts with in the table, summaries and CSV output. The table and summaries use one decimal place by default, and CSV output writes values to at most three decimal places. JSON output always keeps full precision."
+    )]
+    precision: Option<usize>,
 
-        for TimeWrapper { time, forecast } in data {
-            times.add_cell(Cell::new(time.format("%H:%M")));
-            status.add_cell(Cell::new(match forecast.status.as_str() {
-                "Cloudy" | "Overcast" => if args.ascii { "CL" } else { "☁" },
-                "Light shower (night)" | "Light shower (day)" | "Heavy shower (day)" | "Heavy shower (night)" => if args.ascii { "SH" } else { "🌧" },
-                "Partly cloudy (night)" | "Partly cloudy (day)" => if args.ascii { "PC" } else { "🌥" },
-                "Sunny day" => if args.ascii { "SU" } else { "☀" },
-                "Clear night" => if args.ascii { "CN" } else { "☾" },
-                "Light snow" | "Heavy snow" => if args.ascii { "SN" } else { "☃" },
-                "Sunny intervals" => if args.ascii { "PC" } else { "🌤" },
-                "Heavy rain" | "Light rain" => if args.ascii { "RA" } else { "☂" },
-                "Sleet" => if args.ascii { "SL" } else { "🌨" },
-                "Thunder shower (night)" | "Thunder shower (day)" => if args.ascii { "TH" } else { "☈" },
-                status => status
-            }));
-            precip.add_cell(Cell::new(format!("{}%", forecast.precipitation)));
-            temp.add_cell(Cell::new(format_temp(forecast.temperature)));
-            feels.add_cell(Cell::new(format_temp(forecast.feels_like)));
-            wind.add_cell(Cell::new(format_speed(forecast.wind_speed)));
-            dir.add_cell(Cell::new(forecast.wind_direction));
-            gust.add_cell(Cell::new(format_speed(forecast.wind_gust)));
-            visib.add_cell(Cell::new(forecast.visibility));
-            humid.add_cell(Cell::new(format!("{}%", forecast.humidity)));
-            uv.add_cell(Cell::new(forecast.uv_index));
-        }
+    #[clap(
+        long, parse(from_os_str), value_name = "PATH",
+        help = "Record forecasts to a SQLite database",
+        long_help = "Record every forecast time that is output into the forecasts table of the SQLite database at this path, creating it if needed. The current time marked by --mark-now isn't recorded, and nothing is recorded with --average, since averages aren't forecasts for a single time. Each forecast is keyed by the location, date, time and the time it was fetched, so repeated runs accumulate a history of forecasts. This happens in addition to the normal output."
+    )]
+    sqlite: Option<PathBuf>,
 
-        if args.ascii {
-            table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
-        } else {
-            table.load_preset(comfy_table::presets::UTF8_BORDERS_ONLY)
-                .apply_modifier(comfy_table::modifiers::UTF8_ROUND_CORNERS);
-        }
+    #[clap(
+        long, requires = "sqlite",
+        help = "Report the accuracy of past forecasts",
+        long_help = "Instead of forecasting, report how accurate the past forecasts recorded in the SQLite database were for this location. Each recorded forecast is compared against the most recently fetched forecast for the same time, giving the mean temperature error, and the rate at which rain (a precipitation chance of 50% or more) was correctly predicted."
+    )]
+    accuracy: bool,
 
-        table.set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
-            .set_header(times)
-            .add_row(status).add_row(precip).add_row(temp).add_row(feels);
-            
-        if args.extra {
-            table.add_row(wind).add_row(dir).add_row(gust)
-                .add_row(visib).add_row(humid).add_row(uv);
-        }
+    #[clap(
+        long, conflicts_with_all = &["json", "ndjson", "flat-json"],
+        help = "Enable Prometheus output",
+        long_help = "Output the forecast for the current hour as metrics in the Prometheus text exposition format, labelled with the location name and geohash. This is suitable for use with the node_exporter textfile collector. Errors are written to stderr, with a non-zero exit status. The day and time range options are ignored in this mode."
+    )]
+    prometheus: bool,
 
-        println!("{}", date.format("%e %B %Y"));
-        println!("{}", table);
-    }
-}
+    #[clap(
+        long, value_name = "ADDR",
+        help = "Serve forecasts over HTTP",
+        long_help = "Start an HTTP server listening on this address, rather than forecasting once. Forecasts are available at /forecast?location=...&day=...&count=..., and are returned in the same format as JSON output mode. The location parameter is required, and ambiguous locations are rejected. Forecasts are cached for ten minutes, so repeated requests are cheap."
+    )]
+    serve: Option<String>,
 
-fn format_json_success(location: Location, data: Vec<DayWrapper>) {
-    serde_json::to_writer(std::io::stdout(), &Output::Data { location, data }).unwrap();
-}
+    #[clap(
+        long, value_name = "CONDITION", number_of_values = 1, parse(try_from_str),
+        help = "Alert when a forecast condition is met",
+        long_help = "Alert when a forecast condition is met at any of the forecast times. Conditions are of the form field>value or field<value, where field is one of precipitation, temperature, feels_like, wind_speed, wind_gust, visibility, humidity, uv_index or snow_probability, and values are in the selected unit system. This may be given multiple times. The first time each condition is met is reported above the forecast, and in JSON output."
+    )]
+    alert: Vec<AlertCondition>,
 
-fn format_json_failure(err: anyhow::Error) {
-    serde_json::to_writer(std::io::stdout(), &Output::Error { error: serde_error::Error::new(&*err) }).unwrap();
-}
+    #[clap(
+        long, requires = "alert",
+        help = "Raise desktop notifications for alerts",
+        long_help = "Raise a desktop notification for each alert condition that is met, stating the location, the condition and the time it is met. If notifications are unavailable, the alert is written to stderr instead. This is intended for scheduled background runs."
+    )]
+    notify: bool,
 
-fn main() {
-    let args = Args::parse();
+    #[clap(
+        long, value_name = "MINUTES", requires = "notify",
+        help = "Minutes before notifying a worsening alert again",
+        long_help = "Each alert is only notified once for the same location, condition and forecast time, so that scheduled runs don't repeat it, with the notifications remembered in the cache directory. If the forecast for an alert that was already notified worsens, such as a higher chance of rain, it is notified again once at least this many minutes have passed since it last was, which is 180 by default."
+    )]
+    renotify_after: Option<u32>,
 
-    match cli_main(args.clone()) {
-        Ok((location, data)) => if !args.json {
-            format_output_success(args, location, data)
-        } else {
-            format_json_success(location, data)
-        },
-        Err(err) => if !args.json {
-            format_output_failure(err)
-        } else {
-            format_json_failure(err)
-        }
+    #[clap(
+        long, conflicts_with_all = &["json", "ndjson", "flat-json", "prometheus"],
+        help = "Enable shell variable output",
+        long_help = "Output the forecast for the current hour as shell variable assignments, such as WEATHER_TEMPERATURE='14.2', suitable for use with eval. Each forecast field is output with its name uppercased and prefixed with WEATHER_, along with WEATHER_LOCATION and WEATHER_TIME. Errors are written to stderr. The day and time range options are ignored in this mode."
+    )]
+    env: bool,
+
+    #[clap(
+        long,
+        help = "Output the coordinates of the location",
+        long_help = "Instead of forecasting, output the latitude and longitude of the location, separated by a comma. In JSON output, the location and its coordinates are output as an object."
+    )]
+    coords: bool,
+
+    #[clap(
+        long,
+        help = "Compare with the previously fetched forecast",
+        long_help = "Compare the forecast with the one fetched the previous time this location was forecast. Temperatures and precipitation chances that have risen or fallen are marked with arrows, and precipitation chances that have newly risen above 50% are marked as new. If nothing has changed, this is reported instead. In JSON output, the previous forecast for each time is added as a field."
+    )]
+    diff: bool,
+
+    #[clap(
+        long, value_name = "LOCATION", min_values = 1,
+        help = "Fetch and cache forecasts for several locations",
+        long_help = "Instead of forecasting, fetch the forecasts for each of these locations and store them in the cache, for later use in offline mode. The locations are fetched concurrently, and ambiguous locations are skipped rather than asking for a preferred location, unless the best match is picked. A progress bar is shown unless non-interactive mode is enabled."
+    )]
+    prefetch: Vec<String>,
+
+    #[clap(
+        long, default_value = "4", value_name = "N",
+        help = "Maximum number of locations to fetch at once",
+        long_help = "The maximum number of locations to fetch at the same time when prefetching, so that large batches don't flood the Met Office with requests. This must be at least one. Results are reported in the order the locations were given regardless."
+    )]
+    concurrency: usize,
+
+    #[clap(
+        long, requires = "prefetch", value_name = "SECONDS",
+        help = "Time limit for prefetching all locations",
+        long_help = "The number of seconds that prefetching may take in total. Once it passes, any locations still being fetched or waiting to be fetched are abandoned and reported as failed, while those already fetched are reported as usual. This stops a few slow locations from holding up a large batch indefinitely."
+    )]
+    deadline: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Add a column for the current time",
+        long_help = "Add an extra column to today's forecast for the current time, interpolated from the surrounding forecasts and marked in the header. Days other than today are unaffected. In JSON output, this time is marked with a now field."
+    )]
+    mark_now: bool,
+
+    #[clap(
+        long,
+        help = "Output sun safety advice for each day",
+        long_help = "Output the times of each day when the UV index is high (6 or above) during daylight, along with a recommendation for sun protection. Sunrise and sunset are used to exclude night-time hours where the location's coordinates are known. In JSON output, the advice is added as a field of each day."
+    )]
+    sun_advice: bool,
+
+    #[clap(
+        long,
+        help = "Recommend the best day to be outside",
+        long_help = "Score each forecast day from its forecast times and report the best day to be outside, along with the figures it was chosen on. Days with less precipitation, less wind, temperatures closer to an ideal and some sunshine score higher. The weights can be tuned in the [best_day] section of the config file, with the keys precipitation, temperature, wind, uv, ideal_temperature (in Celsius) and max_uv. In JSON output, the best day is added as a field."
+    )]
+    best_day: bool,
+
+    #[clap(
+        long,
+        help = "Suggest what to wear each day",
+        long_help = "Output a short suggestion of what to wear or take each day, based on the coldest feels-like temperature, the highest chance of precipitation and the strongest wind over the forecast times. In JSON output, the suggestion and the items it mentions are added as a field of each day."
+    )]
+    advice: bool,
+
+    #[clap(
+        long,
+        help = "Don't output the forecast banner",
+        long_help = "Don't output the \"Forecast for\" line naming the location above the forecast. This has no effect on JSON output."
+    )]
+    no_header: bool,
+
+    #[clap(
+        long,
+        help = "Don't output the date of each day",
+        long_help = "Don't output the line giving the date and daylight hours above each day's forecast. This has no effect on JSON output."
+    )]
+    no_dates: bool,
+
+    #[clap(
+        long,
+        help = "Output the latest day first",
+        long_help = "Output the days of the forecast in reverse order, so that the latest day comes first and the soonest last, in both text and JSON output. The times within each day are still in order."
+    )]
+    reverse: bool,
+
+    #[clap(
+        long, arg_enum, value_name = "UNIT",
+        help = "Unit to show pressure in",
+        long_help = "The unit to show pressure in, where it is available. The default is hpa (hectopascals, equivalent to millibars), or inhg (inches of mercury) with US customary units. Structured output gives pressures in the chosen unit as well, which is named in the meta field of JSON output, a pressure_unit field or column of NDJSON, flat JSON, CSV and template output, WEATHER_PRESSURE_UNIT with --env, and the metric name with --prometheus."
+    )]
+    pressure_unit: Option<PressureUnit>,
+
+    #[clap(
+        long, arg_enum, value_name = "LANGUAGE", default_value = "en",
+        help = "Language to show weather statuses in",
+        long_help = "The language of the status codes in the forecast table, out of en (English) and fr (French). Statuses that have no translation are shown in English. Structured output always gives statuses by their English labels, so it doesn't depend on this."
+    )]
+    language: Language,
+
+    #[clap(
+        long,
+        help = "Show the URLs that would be fetched",
+        long_help = "Instead of forecasting, output the URLs that would be fetched for this location, without using the network. The location search URL is always shown, and the forecast URL is shown too if the search result is already cached. In JSON output, the URLs are output as an array."
+    )]
+    dry_run: bool,
+
+    #[clap(
+        long, conflicts_with = "dry-run",
+        help = "List the locations matching a search and exit",
+        long_help = "Instead of forecasting, output every location matching the search term, in the Met Office's order of relevance, without picking one. Each location is given with its area and geohash. In JSON output, the search term and matching locations are output as an object, for building your own location picker."
+    )]
+    search_only: bool,
+
+    #[clap(
+        long,
+        help = "Explain how each forecast was derived",
+        long_help = "Output which of the Met Office's forecast times each shown time was derived from. Times between two forecasts are interpolated, and the weight given to the later forecast is shown. In JSON output, the source of each time is added as a field. Cached results that can't be read, and are fetched again instead, are also noted on stderr."
+    )]
+    explain: bool,
+
+    #[clap(
+        long,
+        help = "Average conditions over each time step",
+        long_help = "Instead of the forecast at each shown time, show the conditions over the interval from that time until the next shown time, sampled every 15 minutes. Temperatures, wind speeds, humidity, visibility and pressure are averaged, while the chance of precipitation, gusts, UV index and chance of snow are the highest over the interval. The last time covers the same length of interval as the one before it, or an hour if it is the only time."
+    )]
+    average: bool,
+
+    #[clap(
+        long, conflicts_with = "average",
+        help = "Show the nearest real forecasts instead of interpolating",
+        long_help = "Instead of interpolating between the Met Office's forecasts for each shown time, show the forecast nearest to that time, labelled with its own time, which may differ from the time asked for. Times asked for that have the same nearest forecast are only shown once."
+    )]
+    round_times: bool,
+
+    #[clap(
+        long,
+        help = "Add units to JSON temperatures and speeds",
+        long_help = "In JSON output, output each temperature, speed and visibility in the forecasts as an object with value and unit fields, such as {\"value\": 14.2, \"unit\": \"celsius\"}, rather than as a bare number. The units are given in the meta field either way. Given with --json-schema, the schema describes this form of output."
+    )]
+    json_units: bool,
+
+    #[clap(
+        long, requires = "json",
+        help = "Output only each day's aggregates in JSON",
+        long_help = "In JSON output, output for each day only its minimum, maximum and mean temperature, peak chance of precipitation, number of hours of the whole day with a greater than 50% chance of precipitation, as in the summary, and weather status, rather than every forecast time. The status is that of the wettest requested time if any has a greater than 50% chance of precipitation, and otherwise the most common at the requested times. The Met Office doesn't forecast rainfall amounts, so the wet hours are given instead of total rainfall."
+    )]
+    json_summary: bool,
+
+    #[clap(
+        long, conflicts_with = "json-summary",
+        help = "Group days by week",
+        long_help = "Output a heading such as \"Week of 12 May\" before the first day of each week, which start on Mondays, so that long forecasts are easier to scan. In JSON output, the days are instead given in a weeks field, as a list of weeks each with the date of its Monday and its days."
+    )]
+    group_weeks: bool,
+
+    #[clap(
+        long, conflicts_with_all = &["group-weeks", "next"],
+        help = "Compare the requested days side by side",
+        long_help = "Output the requested days as one table, with a group of columns for each day, so that the same times on different days can be compared. Only times forecast on every day are shown. This only changes the table output."
+    )]
+    compare_days: bool,
+
+    #[clap(
+        long,
+        help = "Print build information as JSON and exit",
+        long_help = "Print information about this build as JSON and exit, including the version, the git commit it was built from, the Rust compiler and target, and the forecast backends it supports. Please include this when reporting a problem."
+    )]
+    version_info: bool,
+
+    #[clap(
+        long,
+        help = "Check that forecasts can still be read, and exit",
+        long_help = "Search for London and fetch its Met Office forecast, bypassing the cache, then check that the forecast has at least one day with temperatures and statuses that could be read. A report of each check is output, and the exit code is nonzero if any failed. This is a quick way to confirm that a change to the Met Office website hasn't broken the scraper."
+    )]
+    self_test: bool,
+
+    #[clap(
+        long,
+        help = "Output weather art for each day",
+        long_help = "Output a few lines of art above each day's forecast, depicting the most common weather at the forecast times, such as sun, cloud, rain or snow. In ASCII mode the art uses only ASCII characters. This has no effect on JSON output."
+    )]
+    art: bool,
+
+    #[clap(
+        long, arg_enum, value_name = "STYLE",
+        help = "Style of the progress spinner",
+        long_help = "The style of the spinner shown while fetching: moon phases, braille dots, clock faces or arrows. In ASCII mode a plain ASCII spinner is always used. A default style can also be set with the style key of the [spinner] section of the config file, along with custom ticks, a string of the spinner's characters, and an indicatif template."
+    )]
+    spinner: Option<SpinnerStyle>,
+
+    #[clap(
+        long, hide = true,
+        help = "Print the JSON output schema and exit"
+    )]
+    json_schema: bool,
+
+    #[clap(skip)]
+    config: config::Config,
+
+    /// Whether to show emoji, decided once from the flags and the terminal.
+    #[clap(skip)]
+    emoji: bool
+}
+
+impl Args {
+    fn fetch_options(&self) -> raw::FetchOptions {
+        let base_url = std::env::var("METOFFICE_BASE_URL").ok()
+            .or_else(|| self.config.base_url.clone())
+            .unwrap_or_else(|| raw::DEFAULT_BASE_URL.to_string());
+
+        let open_meteo_url = std::env::var("OPEN_METEO_BASE_URL").unwrap_or_else(|_| openmeteo::DEFAULT_BASE_URL.to_string());
+        let what3words_url = std::env::var("WHAT3WORDS_BASE_URL").unwrap_or_else(|_| what3words::DEFAULT_BASE_URL.to_string());
+        let what3words_key = std::env::var("WHAT3WORDS_API_KEY").ok()
+            .or_else(|| self.config.what3words_key.clone())
+            .filter(|key| !key.trim().is_empty());
+
+        raw::FetchOptions {
+            policy: self.cache_policy(),
+            location_policy: self.location_cache_policy(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            open_meteo_url: open_meteo_url.trim_end_matches('/').to_string(),
+            what3words_url: what3words_url.trim_end_matches('/').to_string(),
+            what3words_key,
+            cache_dir: self.cache_dir.clone().or_else(|| self.config.cache_dir.clone()).or_else(cache::default_dir),
+            selectors: self.config.selectors.clone(),
+            strict: self.strict,
+            store: true,
+            explain: self.explain
+        }
+    }
+
+    fn cache_policy(&self) -> CachePolicy {
+        if self.offline {
+            CachePolicy::Offline
+        } else if self.serve.is_some() {
+            CachePolicy::MaxAge(Duration::minutes(10))
+        } else {
+            CachePolicy::Refresh
+        }
+    }
+
+    fn location_cache_policy(&self) -> CachePolicy {
+        match self.cache_policy() {
+            CachePolicy::Offline => CachePolicy::Offline,
+            _ if self.location_cache_days > 0 => CachePolicy::MaxAge(Duration::days(self.location_cache_days)),
+            policy => policy
+        }
+    }
+
+    fn search_options(&self) -> raw::SearchOptions {
+        raw::SearchOptions {
+            filters: if self.beaches { vec![LocationFilter::Beaches] } else { vec![] },
+            region: self.region.clone(),
+            pick_best: self.pick_best,
+            max_results: self.max_results,
+            explain: self.explain_location
+        }
+    }
+
+    fn status_threshold(&self) -> Option<f32> {
+        self.status_threshold.or(self.config.status_threshold)
+    }
+
+    fn pressure_unit(&self) -> PressureUnit {
+        match self.pressure_unit {
+            Some(unit) => unit,
+            None if self.freedom_units => PressureUnit::Inhg,
+            None => PressureUnit::Hpa
+        }
+    }
+
+    fn columns(&self) -> Vec<Column> {
+        if self.wind {
+            return vec![Column::Wind, Column::Dir, Column::Gust]
+        }
+
+        if !self.columns.is_empty() {
+            let mut columns = Vec::new();
+            for &column in &self.columns {
+                if !columns.contains(&column) {
+                    columns.push(column);
+                }
+            }
+            return columns
+        }
+
+        let mut columns = vec![Column::Status, Column::Precip, Column::Temp, Column::Feels];
+        if self.extra {
+            columns.extend([Column::Wind, Column::Dir, Column::Gust, Column::Visibility, Column::Humidity, Column::Uv, Column::Pressure]);
+        }
+        columns
+    }
+
+    fn hours(&self) -> Vec<u32> {
+        let hours = match self.preset {
+            Some(preset) => preset.hours(),
+            None => self.time_range.hours()
+        };
+
+        let Some(around) = self.around else { return hours };
+        let mut hours: Vec<u32> = hours.into_iter()
+            .flat_map(|hour| hour.saturating_sub(around)..=(hour + around).min(23))
+            .collect();
+        hours.sort_unstable();
+        hours.dedup();
+        hours
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Day {
+    Offset(usize),
+    Weekday(Weekday)
+}
+
+impl FromStr for Day {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(offset) = s.parse() {
+            return Ok(Day::Offset(offset))
+        }
+
+        s.trim().parse().map(Day::Weekday).map_err(|_| anyhow!("expected a number of days or a weekday"))
+    }
+}
+
+impl Day {
+    fn offset(self, today: NaiveDate) -> usize {
+        match self {
+            Day::Offset(offset) => offset,
+            Day::Weekday(weekday) => (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) as usize % 7
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum TimeRange {
+    Steps { start: usize, step: usize, count: usize },
+    Hours(Vec<u32>)
+}
+
+impl FromStr for TimeRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let list_regex = regex::Regex::new("^(0?[0-9]|1[0-9]|2[0-3])(,(0?[0-9]|1[0-9]|2[0-3]))*$")?;
+        if list_regex.is_match(s) {
+            let mut hours = s.split(',').map(str::parse).collect::<Result<Vec<u32>, _>>()?;
+            hours.sort_unstable();
+            hours.dedup();
+            return Ok(TimeRange::Hours(hours))
+        }
+
+        let fmt_regex = regex::Regex::new("^(0?[0-9]|1[0-9]|2[0-3]):(0?[0-9]|1[0-9]|2[0-4]):(0?[0-9]|1[0-9]|2[0-4])$")?;
+        let caps = fmt_regex.captures(s).context("see --help for correct format")?;
+        let start = caps.get(1).context("regex error")?.as_str().parse()?;
+        let step = caps.get(2).context("regex error")?.as_str().parse()?;
+        let count = caps.get(3).context("regex error")?.as_str().parse()?;
+        if step == 0 || count == 0 {
+            Err(anyhow!("the step and count of a time range must be at least 1"))
+        } else if start + (count - 1)*step >= 24 {
+            Err(anyhow!("this time range overlaps the next day"))
+        } else {
+            Ok(TimeRange::Steps { start, step, count })
+        }
+    }
+}
+
+impl TimeRange {
+    fn hours(&self) -> Vec<u32> {
+        match self {
+            TimeRange::Steps { start, step, count } => (0..*count).map(|i| (start + i*step) as u32).filter(|hour| *hour < 24).collect(),
+            TimeRange::Hours(hours) => hours.clone()
+        }
+    }
+}
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq)]
+enum Column {
+    Status,
+    Precip,
+    Temp,
+    Feels,
+    Wind,
+    Dir,
+    Gust,
+    Visibility,
+    Humidity,
+    Uv,
+    Pressure
+}
+
+#[derive(ArgEnum, Debug, Clone, Copy)]
+enum Preset {
+    Morning,
+    Daytime,
+    Commute,
+    Overnight,
+    Hourly
+}
+
+impl Preset {
+    fn hours(self) -> Vec<u32> {
+        match self {
+            Preset::Morning => (6..=12).collect(),
+            Preset::Daytime => (8..=20).step_by(2).collect(),
+            Preset::Commute => vec![7, 8, 17, 18],
+            Preset::Overnight => (0..=6).step_by(2).collect(),
+            Preset::Hourly => (0..24).collect()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Source {
+    Exact { time: NaiveTime },
+    Interpolated { from: NaiveTime, to: NaiveTime, weight: f32 },
+    Averaged { from: NaiveTime, to: NaiveTime, samples: usize }
+}
+
+/// Pairs each hour with the end of the interval it starts, for averaging.
+fn intervals(hours: &[u32]) -> Vec<(u32, u32)> {
+    hours.iter().enumerate().map(|(i, &hour)| {
+        let end = match (hours.get(i + 1), i.checked_sub(1).map(|j| hours[j])) {
+            (Some(&next), _) => next,
+            (None, Some(before)) => hour + (hour - before),
+            (None, None) => hour + 1
+        };
+        (hour, end.min(24))
+    }).collect()
+}
+
+struct Mixer {
+    data: Vec<(NaiveTime, Forecast)>,
+    status_threshold: Option<f32>,
+    nearest_status: bool
+}
+
+impl Mixer {
+    fn new(mut data: Vec<(NaiveTime, Forecast)>) -> Mixer {
+        // Duplicate times would make the interpolation divide by zero, so keep only the last of each.
+        data.sort_by_key(|(time, _)| *time);
+        data.reverse();
+        data.dedup_by_key(|(time, _)| *time);
+        data.reverse();
+        Mixer { data, status_threshold: None, nearest_status: false }
+    }
+
+    fn with_status_threshold(self, status_threshold: Option<f32>) -> Mixer {
+        Mixer { status_threshold, ..self }
+    }
+
+    fn with_nearest_status(self, nearest_status: bool) -> Mixer {
+        Mixer { nearest_status, ..self }
+    }
+
+    fn blend_status(&self, t: f32, afore: &Forecast, bfore: &Forecast) -> WeatherStatus {
+        if self.nearest_status {
+            return if t < 0.5 { afore.status.clone() } else { bfore.status.clone() }
+        }
+        let Some(threshold) = self.status_threshold else {
+            return if bfore.precipitation > afore.precipitation { bfore.status.clone() } else { afore.status.clone() }
+        };
+
+        let (nearer, other) = if t < 0.5 { (afore, bfore) } else { (bfore, afore) };
+        if other.precipitation.is_some_and(|p| p >= threshold) && nearer.precipitation.is_some_and(|p| p < threshold) {
+            other.status.clone()
+        } else {
+            nearer.status.clone()
+        }
+    }
+
+    fn source(&self, time: NaiveTime) -> Option<Source> {
+        match self.data.binary_search_by_key(&time, |(time, _)| *time) {
+            Err(idx) if idx == 0 || idx == self.data.len() => None,
+            Ok(idx) => Some(Source::Exact { time: self.data[idx].0 }),
+            Err(idx) => {
+                let from = self.data[idx - 1].0;
+                let to = self.data[idx].0;
+                let weight = (time - from).num_minutes() as f32 / (to - from).num_minutes() as f32;
+                Some(Source::Interpolated { from, to, weight })
+            }
+        }
+    }
+
+    fn samples(&self, from: u32, to: u32) -> Vec<(NaiveTime, Forecast)> {
+        (from * 60..to * 60).step_by(15)
+            .map(|minutes| NaiveTime::from_hms(minutes / 60, minutes % 60, 0))
+            .filter_map(|time| self.lerp(time).map(|forecast| (time, forecast)))
+            .collect()
+    }
+
+    /// The conditions over the hours from `from` until `to`, or `None` if there are no forecasts in that time.
+    fn average(&self, from: u32, to: u32) -> Option<(Forecast, Source)> {
+        let samples = self.samples(from, to);
+        let (_, wettest) = samples.iter().max_by(|(_, a), (_, b)| a.precipitation.partial_cmp(&b.precipitation).unwrap_or(std::cmp::Ordering::Equal))?;
+        let values = |field: fn(&Forecast) -> Option<f32>| samples.iter().filter_map(move |(_, forecast)| field(forecast));
+        let mean = |field: fn(&Forecast) -> Option<f32>| {
+            let count = values(field).count();
+            if count == 0 { None } else { Some(values(field).sum::<f32>() / count as f32) }
+        };
+        let max = |field: fn(&Forecast) -> Option<f32>| values(field).reduce(f32::max);
+
+        let forecast = Forecast {
+            status: wettest.status.clone(),
+            precipitation: max(|f| f.precipitation),
+            temperature: mean(|f| f.temperature),
+            feels_like: mean(|f| f.feels_like),
+            wind_speed: mean(|f| f.wind_speed),
+            wind_direction: samples[0].1.wind_direction.clone(),
+            wind_gust: max(|f| f.wind_gust),
+            visibility: mean(|f| f.visibility),
+            humidity: mean(|f| f.humidity),
+            uv_index: max(|f| f.uv_index),
+            pressure: mean(|f| f.pressure),
+            snow_probability: max(|f| f.snow_probability),
+            snow_amount: max(|f| f.snow_amount)
+        };
+        let from = samples[0].0;
+        let to = NaiveTime::from_hms(to % 24, 0, 0);
+        Some((forecast, Source::Averaged { from, to, samples: samples.len() }))
+    }
+
+    /// The real forecast nearest to a time, as long as the time is within the forecasts.
+    fn nearest(&self, time: NaiveTime) -> Option<(NaiveTime, Forecast)> {
+        match self.data.binary_search_by_key(&time, |(time, _)| *time) {
+            Err(idx) if idx == 0 || idx == self.data.len() => None,
+            Ok(idx) => Some(self.data[idx].clone()),
+            Err(idx) => {
+                let (before, after) = (&self.data[idx - 1], &self.data[idx]);
+                Some(if time - before.0 <= after.0 - time { before.clone() } else { after.clone() })
+            }
+        }
+    }
+
+    fn lerp(&self, time: NaiveTime) -> Option<Forecast> {
+        match self.data.binary_search_by_key(&time, |(time, _)| *time) {
+            Err(idx) if idx == 0 || idx == self.data.len() => None,
+            Ok(idx) => Some(self.data[idx].1.clone()),
+            Err(idx) => {
+                let (atime, afore) = self.data[idx - 1].clone();
+                let (btime, bfore) = self.data[idx].clone();
+                let t = (time - atime).num_minutes() as f32 / (btime - atime).num_minutes() as f32;
+                // A value missing from either side is missing from the interpolation.
+                let mix = |a: Option<f32>, b: Option<f32>| a.zip(b).map(|(a, b)| (1.0 - t)*a + t*b);
+
+                Some(Forecast {
+                    status: self.blend_status(t, &afore, &bfore),
+                    precipitation: mix(afore.precipitation, bfore.precipitation),
+                    temperature: mix(afore.temperature, bfore.temperature),
+                    feels_like: mix(afore.feels_like, bfore.feels_like),
+                    wind_speed: mix(afore.wind_speed, bfore.wind_speed),
+                    wind_direction: if self.nearest_status && t >= 0.5 { bfore.wind_direction } else { afore.wind_direction },
+                    wind_gust: mix(afore.wind_gust, bfore.wind_gust),
+                    visibility: mix(afore.visibility, bfore.visibility),
+                    humidity: mix(afore.humidity, bfore.humidity),
+                    uv_index: afore.uv_index.zip(bfore.uv_index).map(|(a, b)| a.max(b)),
+                    pressure: mix(afore.pressure, bfore.pressure),
+                    snow_probability: mix(afore.snow_probability, bfore.snow_probability),
+                    snow_amount: mix(afore.snow_amount, bfore.snow_amount)
+                })
+            }
+        }
+        
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(untagged)]
+enum Output {
+    Data {
+        location: Location,
+        fetched_at: DateTime<Utc>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        stale: bool,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        alerts: Vec<Alert>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        truncated: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        best_day: Option<BestDay>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        summary_text: Option<String>,
+        meta: Meta,
+        data: Vec<DayWrapper>
+    },
+    Summary {
+        location: Location,
+        fetched_at: DateTime<Utc>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        stale: bool,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        alerts: Vec<Alert>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        truncated: bool,
+        meta: Meta,
+        data: Vec<DayAggregate>
+    },
+    Weeks {
+        location: Location,
+        fetched_at: DateTime<Utc>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        stale: bool,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        alerts: Vec<Alert>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        truncated: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        best_day: Option<BestDay>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        summary_text: Option<String>,
+        meta: Meta,
+        weeks: Vec<Week>
+    },
+    Wind {
+        location: Location,
+        fetched_at: DateTime<Utc>,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        stale: bool,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        truncated: bool,
+        meta: Meta,
+        data: Vec<WindDay>
+    },
+    Accuracy { location: Location, accuracy: history::Accuracy },
+    Coordinates { location: Location, latitude: f32, longitude: f32 },
+    Prefetch { prefetched: Vec<Prefetched> },
+    DryRun { urls: Vec<String> },
+    SelfTest { passed: bool, checks: Vec<SelfTestCheck> },
+    ClearCache { removed: usize },
+    Locations { query: String, results: Vec<Location> },
+    Error {
+        #[schemars(with = "ErrorSchema")]
+        error: serde_error::Error
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct VersionInfo {
+    version: &'static str,
+    git_commit: Option<&'static str>,
+    rustc: &'static str,
+    target: &'static str,
+    backends: Vec<&'static str>
+}
+
+fn version_info() -> VersionInfo {
+    VersionInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: Some(env!("WEATHER_GIT_COMMIT")).filter(|commit| !commit.is_empty()),
+        rustc: env!("WEATHER_RUSTC_VERSION"),
+        target: env!("WEATHER_TARGET"),
+        backends: Backend::value_variants().iter().filter_map(|backend| Some(backend.to_possible_value()?.get_name())).collect()
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct Meta {
+    provider: Backend,
+    temperature_unit: &'static str,
+    speed_unit: &'static str,
+    visibility_unit: &'static str,
+    pressure_unit: PressureUnit,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timezone: Option<raw::TimeZone>
+}
+
+/// Mirrors the serialized form of `serde_error::Error`, which doesn't implement `JsonSchema`.
+#[derive(JsonSchema)]
+#[schemars(rename = "Error")]
+#[allow(dead_code)]
+struct ErrorSchema {
+    description: String,
+    source: Option<Box<ErrorSchema>>
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct Week {
+    week_of: NaiveDate,
+    days: Vec<DayWrapper>
+}
+
+/// The Monday starting the week of a date.
+fn week_of(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct DayWrapper {
+    date: NaiveDate,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<DaySummary>,
+    #[serde(serialize_with = "serialize_minutes")]
+    #[schemars(with = "Option<i64>")]
+    daylight: Option<Duration>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tides: Vec<Tide>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sea_temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pressure_trend: Option<PressureTrend>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    sun_advice: Vec<SunAdvice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    advice: Option<ClothingAdvice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snow: Option<SnowSummary>,
+    /// Worked out from the whole day, for the summary and aggregates.
+    #[serde(skip)]
+    wet_hours: usize,
+    times: Vec<TimeWrapper>
+}
+
+fn serialize_minutes<S: serde::Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serializer.serialize_some(&duration.num_minutes()),
+        None => serializer.serialize_none()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum PressureTrend {
+    Rising,
+    Steady,
+    Falling
+}
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq)]
+enum Language {
+    En,
+    Fr
+}
+
+impl Language {
+    fn code(self) -> &'static str {
+        match self {
+            Language::En => "en",
+            Language::Fr => "fr"
+        }
+    }
+}
+
+#[derive(ArgEnum, Debug, Clone, Copy, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum PressureUnit {
+    Hpa,
+    Inhg,
+    Mmhg
+}
+
+impl PressureUnit {
+    fn convert(self, hpa: f32) -> f32 {
+        match self {
+            PressureUnit::Hpa => hpa,
+            PressureUnit::Inhg => hpa * 0.02953,
+            PressureUnit::Mmhg => hpa * 0.75006
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PressureUnit::Hpa => "hpa",
+            PressureUnit::Inhg => "inhg",
+            PressureUnit::Mmhg => "mmhg"
+        }
+    }
+
+    fn format(self, hpa: f32) -> String {
+        match self {
+            PressureUnit::Hpa => format!("{:.0}hPa", self.convert(hpa)),
+            PressureUnit::Inhg => format!("{:.2}inHg", self.convert(hpa)),
+            PressureUnit::Mmhg => format!("{:.0}mmHg", self.convert(hpa))
+        }
+    }
+}
+
+fn pressure_trend(times: &[TimeWrapper]) -> Option<PressureTrend> {
+    let mut pressures = times.iter().filter_map(|time| time.forecast.pressure);
+    let first = pressures.next()?;
+    let last = pressures.next_back()?;
+
+    Some(if last - first > 1.0 {
+        PressureTrend::Rising
+    } else if first - last > 1.0 {
+        PressureTrend::Falling
+    } else {
+        PressureTrend::Steady
+    })
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct DaySummary {
+    wet_hours: usize,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    summary_status: String
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct WindDay {
+    date: NaiveDate,
+    times: Vec<WindTime>
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct WindTime {
+    time: NaiveTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datetime: Option<DateTime<FixedOffset>>,
+    wind_speed: Option<f32>,
+    wind_direction: Option<String>,
+    wind_gust: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gust_severity: Option<GustSeverity>
+}
+
+fn wind_day(day: DayWrapper) -> WindDay {
+    let times = day.times.into_iter().map(|TimeWrapper { time, datetime, gust_severity, forecast, .. }| WindTime {
+        time,
+        datetime,
+        wind_speed: forecast.wind_speed,
+        wind_direction: forecast.wind_direction,
+        wind_gust: forecast.wind_gust,
+        gust_severity
+    }).collect();
+    WindDay { date: day.date, times }
+}
+
+/// An arrow pointing the way the wind blows, given the compass point it blows from.
+fn wind_arrow(direction: &str) -> Option<char> {
+    const POINTS: [&str; 16] = ["N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW"];
+    const ARROWS: [char; 8] = ['↓', '↙', '←', '↖', '↑', '↗', '→', '↘'];
+    let index = POINTS.iter().position(|point| *point == direction)?;
+    Some(ARROWS[index.div_ceil(2) % 8])
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct DayAggregate {
+    date: NaiveDate,
+    min_temperature: Option<f32>,
+    max_temperature: Option<f32>,
+    mean_temperature: Option<f32>,
+    peak_precipitation: Option<f32>,
+    wet_hours: usize,
+    #[schemars(with = "String")]
+    status: WeatherStatus
+}
+
+/// Aggregates a day's forecasts at the requested times, leaving out the current time marker. The status is
+/// that of the wettest time if any is likely to be wet, and otherwise the most common.
+fn aggregate_day(day: &DayWrapper) -> Option<DayAggregate> {
+    let forecasts: Vec<&Forecast> = day.times.iter().filter(|time| !time.now).map(|time| &time.forecast).collect();
+    if forecasts.is_empty() {
+        return None
+    }
+
+    let temperatures: Vec<f32> = forecasts.iter().filter_map(|forecast| forecast.temperature).collect();
+    let precipitations = || forecasts.iter().filter_map(|forecast| forecast.precipitation);
+    let count = |status: &WeatherStatus| forecasts.iter().filter(|forecast| forecast.status == *status).count();
+    let wettest = forecasts.iter()
+        .filter(|forecast| forecast.precipitation.is_some_and(|p| p > 50.0))
+        .max_by(|a, b| a.precipitation.partial_cmp(&b.precipitation).unwrap_or(std::cmp::Ordering::Equal));
+    let status = match wettest {
+        Some(forecast) => forecast.status.clone(),
+        None => forecasts.iter().rev().map(|forecast| &forecast.status).max_by_key(|status| count(status)).cloned().unwrap_or_default()
+    };
+
+    Some(DayAggregate {
+        date: day.date,
+        min_temperature: temperatures.iter().copied().reduce(f32::min),
+        max_temperature: temperatures.iter().copied().reduce(f32::max),
+        mean_temperature: if temperatures.is_empty() { None } else { Some(temperatures.iter().sum::<f32>() / temperatures.len() as f32) },
+        peak_precipitation: precipitations().reduce(f32::max),
+        wet_hours: day.wet_hours,
+        status
+    })
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct SnowSummary {
+    hours: Vec<NaiveTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total_amount: Option<f32>
+}
+
+fn summarise_snow(mixer: &Mixer) -> SnowSummary {
+    let hours = (0..24)
+        .map(|hour| NaiveTime::from_hms(hour, 0, 0))
+        .filter(|time| mixer.lerp(*time).and_then(|forecast| forecast.snow_probability).is_some_and(|p| p >= 50.0))
+        .collect();
+    let amounts: Vec<f32> = mixer.data.iter().filter_map(|(_, forecast)| forecast.snow_amount).collect();
+    let total_amount = if amounts.is_empty() { None } else { Some(amounts.iter().sum()) };
+
+    SnowSummary { hours, total_amount }
+}
+
+/// The number of hours of the day with a greater than 50% chance of precipitation, from the whole day
+/// rather than just the requested times.
+fn wet_hours(mixer: &Mixer) -> usize {
+    (0..24)
+        .filter_map(|hour| mixer.lerp(NaiveTime::from_hms(hour, 0, 0)))
+        .filter(|forecast| forecast.precipitation.is_some_and(|p| p > 50.0))
+        .count()
+}
+
+/// What falls at a wet time, judged from its status, or just rain if only the precipitation chance is high.
+fn wet_kind(forecast: &Forecast) -> Option<&'static str> {
+    match (&forecast.status, forecast.status.condition()) {
+        (WeatherStatus::LightShowerDay | WeatherStatus::LightShowerNight | WeatherStatus::HeavyShowerDay | WeatherStatus::HeavyShowerNight, _) => Some("showers"),
+        (_, Condition::Rain) => Some("rain"),
+        (_, Condition::Snow) => Some("snow"),
+        (_, Condition::Thunder) => Some("thunderstorms"),
+        _ if forecast.precipitation.is_some_and(|p| p > 50.0) => Some("rain"),
+        _ => None
+    }
+}
+
+/// Describes a day's weather at the given times in a few words, such as "mostly cloudy with afternoon showers",
+/// from the prevailing condition at the dry times and when the wet times fall.
+fn day_summary_status(times: &[TimeWrapper]) -> String {
+    let times: Vec<&TimeWrapper> = times.iter().filter(|time| !time.now).collect();
+    let (wet, dry): (Vec<&TimeWrapper>, Vec<&TimeWrapper>) = times.iter().partition(|time| wet_kind(&time.forecast).is_some());
+
+    let count = |status: &WeatherStatus| dry.iter().filter(|time| time.forecast.status == *status).count();
+    let prevailing = dry.iter().rev().map(|time| &time.forecast.status).max_by_key(|status| count(status));
+    let prevailing = prevailing.map(|status| {
+        let description = match status.condition() {
+            Condition::Clear => "sunny".to_string(),
+            Condition::ClearNight => "clear".to_string(),
+            Condition::PartlyCloudy => "partly cloudy".to_string(),
+            Condition::Cloudy => "cloudy".to_string(),
+            _ => status.label().to_lowercase()
+        };
+        if count(status) < times.len() && status.condition() != Condition::PartlyCloudy {
+            format!("mostly {}", description)
+        } else {
+            description
+        }
+    });
+
+    let kinds: Vec<&str> = wet.iter().filter_map(|time| wet_kind(&time.forecast)).collect();
+    let kind = kinds.iter().rev().max_by_key(|kind| kinds.iter().filter(|k| k == kind).count());
+    let mut periods: Vec<&str> = Vec::new();
+    for time in &wet {
+        let period = match time.time.hour() {
+            0..=5 => "overnight",
+            6..=11 => "morning",
+            12..=17 => "afternoon",
+            _ => "evening"
+        };
+        if !periods.contains(&period) {
+            periods.push(period);
+        }
+    }
+    let periods = match periods.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => periods.join("")
+    };
+
+    match (prevailing, kind) {
+        (Some(prevailing), Some(kind)) => format!("{} with {} {}", prevailing, periods, kind),
+        (Some(prevailing), None) => prevailing,
+        (None, Some(kind)) => format!("{} throughout", kind),
+        (None, None) => String::new()
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct TimeWrapper {
+    time: NaiveTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datetime: Option<DateTime<FixedOffset>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    now: bool,
+    feels_like_delta: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gust_severity: Option<GustSeverity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<Source>,
+    forecast: Forecast,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    previous: Option<Forecast>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    other_units: Option<OtherUnits>
+}
+
+/// A forecast's temperatures and speeds in the unit system that wasn't chosen.
+#[derive(Debug, Serialize, JsonSchema)]
+struct OtherUnits {
+    temperature_unit: &'static str,
+    speed_unit: &'static str,
+    temperature: Option<f32>,
+    feels_like: Option<f32>,
+    wind_speed: Option<f32>,
+    wind_gust: Option<f32>
+}
+
+impl OtherUnits {
+    fn new(forecast: &Forecast, freedom_units: bool) -> OtherUnits {
+        let (temperature_unit, speed_unit) = if freedom_units { ("celsius", "kph") } else { ("fahrenheit", "mph") };
+        OtherUnits {
+            temperature_unit,
+            speed_unit,
+            temperature: forecast.temperature.map(|t| other_temperature(t, freedom_units)),
+            feels_like: forecast.feels_like.map(|t| other_temperature(t, freedom_units)),
+            wind_speed: forecast.wind_speed.map(|s| other_speed(s, freedom_units)),
+            wind_gust: forecast.wind_gust.map(|s| other_speed(s, freedom_units))
+        }
+    }
+}
+
+fn other_temperature(t: f32, freedom_units: bool) -> f32 {
+    if freedom_units { units::celsius(t) } else { units::fahrenheit(t) }
+}
+
+fn other_speed(s: f32, freedom_units: bool) -> f32 {
+    if freedom_units { units::kph(s) } else { units::mph(s) }
+}
+
+impl TimeWrapper {
+    fn new(time: NaiveTime, forecast: Forecast) -> TimeWrapper {
+        TimeWrapper { time, datetime: None, now: false, feels_like_delta: feels_like_delta(&forecast), gust_severity: None, source: None, forecast, previous: None, other_units: None }
+    }
+}
+
+fn feels_like_delta(forecast: &Forecast) -> Option<f32> {
+    Some(forecast.feels_like? - forecast.temperature?)
+}
+
+fn feels_like_diverges(forecast: &Forecast, freedom_units: bool) -> bool {
+    let threshold = units::temperature_difference(3.0, freedom_units);
+    feels_like_delta(forecast).is_some_and(|delta| delta.abs() > threshold)
+}
+
+fn temperature_changed(forecast: &Forecast, previous: &Forecast) -> bool {
+    forecast.temperature.zip(previous.temperature).is_some_and(|(t, p)| (t - p).abs() >= 0.5)
+}
+
+fn precipitation_changed(forecast: &Forecast, previous: &Forecast) -> bool {
+    forecast.precipitation.zip(previous.precipitation).is_some_and(|(t, p)| (t - p).abs() >= 10.0)
+}
+
+#[derive(Debug, Serialize)]
+struct FlatRecord<'a> {
+    #[serde(flatten)]
+    location: &'a Location,
+    date: NaiveDate,
+    time: NaiveTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    datetime: Option<DateTime<FixedOffset>>,
+    #[serde(flatten)]
+    forecast: &'a Forecast,
+    pressure_unit: PressureUnit
+}
+
+#[derive(ArgEnum, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum SpinnerStyle {
+    Moon,
+    Dots,
+    Clock,
+    Arrows
+}
+
+impl SpinnerStyle {
+    fn ticks(self, emoji: bool) -> &'static str {
+        match self {
+            SpinnerStyle::Moon | SpinnerStyle::Clock if !emoji => "|/-\\",
+            SpinnerStyle::Moon => "🌑🌒🌓🌔🌕🌖🌗🌘",
+            SpinnerStyle::Dots => "⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏",
+            SpinnerStyle::Clock => "🕛🕐🕑🕒🕓🕔🕕🕖🕗🕘🕙🕚",
+            SpinnerStyle::Arrows => "←↖↑↗→↘↓↙"
+        }
+    }
+}
+
+/// Guesses whether the terminal can display emoji, which older consoles can't even when they support UTF8.
+fn emoji_supported() -> bool {
+    if cfg!(windows) {
+        std::env::var_os("WT_SESSION").is_some()
+    } else {
+        std::env::var("TERM").map_or(true, |term| term != "linux")
+    }
+}
+
+fn make_spinner(args: &Args) -> ProgressBar {
+    let config = &args.config.spinner;
+    let ticks = match (args.spinner, &config.ticks) {
+        _ if args.ascii => "|/-\\",
+        (Some(style), _) => style.ticks(args.emoji),
+        (None, Some(ticks)) => ticks,
+        (None, None) => config.style.unwrap_or(SpinnerStyle::Moon).ticks(args.emoji)
+    };
+    let spinner_style = ProgressStyle::default_spinner()
+        .tick_chars(ticks)
+        .template(config.template.as_deref().unwrap_or("{prefix:.bold.dim} {spinner} {wide_msg}"));
+
+    let bar = ProgressBar::new_spinner();
+    if !args.non_interactive {
+        bar.set_style(spinner_style);
+        bar.set_message("Finding location");
+        bar.enable_steady_tick(100);
+    }
+
+    bar
+}
+
+fn find_location(args: &Args, bar: &ProgressBar) -> Result<(Location, String)> {
+    if args.interactive_search {
+        bar.finish_and_clear();
+        let location = picker::pick_location(&args.search_options().filters, args.ascii, &args.fetch_options())?.context("No location was picked.")?;
+        bar.reset();
+        bar.enable_steady_tick(100);
+        return with_geohash(Some(location))
+    }
+
+    let location = raw::get_location(args.location.clone(), &args.search_options(), args.non_interactive, args.ascii, &args.fetch_options(), bar.clone())?;
+    let found = with_geohash(location);
+    if found.is_err() && !args.non_interactive {
+        bar.finish_and_clear();
+    }
+
+    found
+}
+
+fn with_geohash(location: Option<Location>) -> Result<(Location, String)> {
+    let location = location.context("That location could not be found: perhaps there is a typo, or your location services are off.")?;
+    let geohash = location.geohash.clone().context("That location is too broad, please pick a more specific location.")?;
+    Ok((location, geohash))
+}
+
+fn accuracy_main(args: Args) -> Result<(Location, history::Accuracy)> {
+    let bar = make_spinner(&args);
+    let (location, geohash) = find_location(&args, &bar)?;
+
+    if !args.non_interactive {
+        bar.finish_and_clear();
+    }
+
+    let path = args.sqlite.as_deref().context("--accuracy requires --sqlite")?;
+    let accuracy = history::accuracy(path, &geohash, args.freedom_units)?;
+    Ok((location, accuracy))
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct SelfTestCheck {
+    name: &'static str,
+    passed: bool,
+    detail: String
+}
+
+/// Runs each check in turn against a fresh forecast for London, stopping at the first that can't be run.
+fn self_test_main(args: &Args) -> Vec<SelfTestCheck> {
+    let options = raw::FetchOptions { policy: CachePolicy::Refresh, location_policy: CachePolicy::Refresh, ..args.fetch_options() };
+    let search = raw::SearchOptions { filters: Vec::new(), region: None, pick_best: true, max_results: 10, explain: false };
+    let mut checks = Vec::new();
+    let mut check = |name, result: Result<String>| {
+        let passed = result.is_ok();
+        let detail = result.unwrap_or_else(|err| format!("{:#}", err));
+        checks.push(SelfTestCheck { name, passed, detail });
+    };
+
+    let found = raw::get_location(Some("London".to_string()), &search, true, args.ascii, &options, ProgressBar::hidden()).and_then(with_geohash);
+    let geohash = match found {
+        Ok((location, geohash)) => {
+            check("location search", Ok(format!("found {} ({})", location.name, geohash)));
+            geohash
+        },
+        Err(err) => {
+            check("location search", Err(err));
+            return checks
+        }
+    };
+
+    let days = match raw::get_forecast(geohash, args.freedom_units, &options) {
+        Ok((_, days)) => {
+            check("forecast", Ok(format!("read {} days", days.len())));
+            days
+        },
+        Err(err) => {
+            check("forecast", Err(err));
+            return checks
+        }
+    };
+
+    let times: Vec<_> = days.iter().flat_map(|day| day.times.iter().map(|(_, forecast)| forecast)).collect();
+    let with_temperature = times.iter().filter(|forecast| forecast.temperature.is_some()).count();
+    let recognised = times.iter().filter(|forecast| !matches!(forecast.status, WeatherStatus::Other(_))).count();
+    check("times", if times.is_empty() { Err(anyhow!("no forecast times")) } else { Ok(format!("read {} times", times.len())) });
+    check("temperatures", match with_temperature {
+        0 => Err(anyhow!("no temperatures could be read")),
+        n => Ok(format!("{} of {} times have a temperature", n, times.len()))
+    });
+    check("statuses", match recognised {
+        0 => Err(anyhow!("no statuses were recognised")),
+        n => Ok(format!("{} of {} times have a recognised status", n, times.len()))
+    });
+    checks
+}
+
+fn format_self_test_success(checks: &[SelfTestCheck]) {
+    for SelfTestCheck { name, passed, detail } in checks {
+        println!("{} {}: {}", if *passed { "PASS" } else { "FAIL" }, name, detail);
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+struct Prefetched {
+    query: String,
+    location: Option<Location>,
+    #[schemars(with = "Option<ErrorSchema>")]
+    error: Option<serde_error::Error>
+}
+
+async fn prefetch_one(client: &reqwest::Client, semaphore: &tokio::sync::Semaphore, args: &Args, query: &str) -> Result<Location> {
+    let _permit = semaphore.acquire().await?;
+    let options = args.fetch_options();
+    let (location, geohash) = with_geohash(raw::get_location_async(client, query, &args.search_options(), &options).await?)?;
+    raw::get_forecast_async(client, &geohash, args.freedom_units, &options).await?;
+    Ok(location)
+}
+
+fn prefetch_main(args: Args) -> Result<Vec<Prefetched>> {
+    let bar = if args.non_interactive {
+        ProgressBar::hidden()
+    } else {
+        let style = ProgressStyle::default_bar()
+            .progress_chars(if args.ascii { "=> " } else { "█▉▊▋▌▍▎▏ " })
+            .template("{prefix:.bold.dim} {bar:30} {pos}/{len} • {wide_msg}");
+        ProgressBar::new(args.prefetch.len() as u64).with_style(style)
+    };
+
+    if args.concurrency == 0 {
+        return Err(anyhow!("The concurrency must be at least one."))
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+    let client = reqwest::Client::new();
+    let semaphore = tokio::sync::Semaphore::new(args.concurrency);
+    let deadline = args.deadline.map(|seconds| tokio::time::Instant::now() + std::time::Duration::from_secs(seconds));
+    let prefetched = runtime.block_on(futures::future::join_all(args.prefetch.iter().map(|query| async {
+        let fetched = match deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline, prefetch_one(&client, &semaphore, &args, query)).await
+                .unwrap_or_else(|_| Err(anyhow!("abandoned because the deadline passed"))),
+            None => prefetch_one(&client, &semaphore, &args, query).await
+        };
+        let (location, error) = match fetched {
+            Ok(location) => (Some(location), None),
+            Err(err) => (None, Some(serde_error::Error::new(&*err)))
+        };
+        bar.set_message(query.clone());
+        bar.inc(1);
+        Prefetched { query: query.clone(), location, error }
+    })));
+
+    bar.finish_and_clear();
+    Ok(prefetched)
+}
+
+struct Report {
+    location: Location,
+    provider: Backend,
+    fetched_at: DateTime<Utc>,
+    current: Option<TimeWrapper>,
+    alerts: Vec<Alert>,
+    previous_fetched_at: Option<DateTime<Utc>>,
+    stale: bool,
+    available_days: usize,
+    truncated: bool,
+    best_day: Option<BestDay>,
+    summary_text: Option<String>,
+    timezone: Option<raw::TimeZone>,
+    data: Vec<DayWrapper>
+}
+
+impl Report {
+    /// Converts every pressure from hectopascals, for output that gives pressures as bare numbers.
+    fn with_pressures_in(mut self, unit: PressureUnit) -> Report {
+        let times = self.data.iter_mut().flat_map(|day| &mut day.times).chain(&mut self.current);
+        for time in times {
+            for forecast in std::iter::once(&mut time.forecast).chain(&mut time.previous) {
+                forecast.pressure = forecast.pressure.map(|p| unit.convert(p));
+            }
+        }
+        self
+    }
+}
+
+fn search_main(args: &Args) -> Result<(String, Vec<Location>)> {
+    let query = args.location.clone().context("A location must be given.")?;
+    let results = raw::search_results(&query, &args.search_options().filters, &args.fetch_options())?
+        .into_iter()
+        .map(Location::with_coordinates)
+        .collect();
+    Ok((query, results))
+}
+
+fn coords_main(args: Args) -> Result<(Location, f32, f32)> {
+    let bar = make_spinner(&args);
+    let (location, _) = find_location(&args, &bar)?;
+
+    if !args.non_interactive {
+        bar.finish_and_clear();
+    }
+
+    let latitude = location.latitude.context("malformed geohash")?;
+    let longitude = location.longitude.context("malformed geohash")?;
+    Ok((location, latitude, longitude))
+}
+
+#[derive(ArgEnum, Debug, Clone, Copy, PartialEq, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+enum Backend {
+    Metoffice,
+    OpenMeteo
+}
+
+impl Backend {
+    fn name(self) -> &'static str {
+        match self {
+            Backend::Metoffice => "Met Office",
+            Backend::OpenMeteo => "Open-Meteo"
+        }
+    }
+}
+
+/// Tries each of the chosen backends in turn, returning the first forecast that could be found.
+fn get_forecast(args: &Args, coordinates: Option<(f32, f32)>, geohash: &str, bar: &ProgressBar) -> Result<(Backend, DateTime<Utc>, Vec<raw::ForecastDay>)> {
+    let options = args.fetch_options();
+    for (i, &backend) in args.backend.iter().enumerate() {
+        if i > 0 && !args.non_interactive {
+            bar.set_message(format!("Trying {} instead", backend.name()));
+        }
+
+        let forecast = match backend {
+            Backend::Metoffice => raw::get_forecast(geohash.to_string(), args.freedom_units, &options),
+            Backend::OpenMeteo => coordinates
+                .context("can't find the coordinates of the location")
+                .and_then(|(latitude, longitude)| openmeteo::get_forecast(latitude, longitude, args.freedom_units, &options))
+        };
+
+        match forecast {
+            Ok((fetched_at, data)) => {
+                if i > 0 {
+                    eprintln!("Using forecast from {}", backend.name());
+                }
+                return Ok((backend, fetched_at, data))
+            },
+            Err(err) if i + 1 < args.backend.len() => eprintln!("Warning: couldn't get a forecast from {}: {:#}", backend.name(), err),
+            Err(err) => return Err(err)
+        }
+    }
+
+    Err(anyhow!("No forecast providers were given."))
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday"
+    }
+}
+
+fn cli_main(args: Args) -> Result<Report> {
+    let bar = make_spinner(&args);
+    let (location, geohash) = find_location(&args, &bar)?;
+
+    if !args.non_interactive {
+        let detail = if args.extra { "detailed " } else { "" };
+        bar.set_message(format!("Getting {}forecast for {} ({})", detail, location.name, location.area.as_deref().unwrap_or("N/A")));
+    }
+
+    let coordinates = location.latitude.zip(location.longitude);
+    let (provider, fetched_at, data) = get_forecast(&args, coordinates, &geohash, &bar)?;
+    let (previous_fetched_at, previous) = if args.diff && provider == Backend::Metoffice {
+        if !args.non_interactive {
+            bar.set_message("Reading the previous forecast");
+        }
+        raw::get_previous_forecast(&geohash, args.freedom_units, &args.fetch_options())?.unzip()
+    } else {
+        (None, None)
+    };
+
+    if !args.non_interactive {
+        bar.finish_and_clear();
+    }
+
+    let now = Local::now().naive_local();
+    let current = data.iter()
+        .find(|day| day.date == now.date())
+        .and_then(|day| {
+            let time = NaiveTime::from_hms(now.hour(), 0, 0);
+            Mixer::new(day.times.clone()).with_status_threshold(args.status_threshold()).with_nearest_status(args.nearest_status).lerp(time)
+                .map(|forecast| TimeWrapper { datetime: Some(day.timezone.datetime(day.date, time)), ..TimeWrapper::new(time, forecast) })
+        });
+
+    let summary_text = args.hours_ahead.and_then(|hours| summarise_hours_ahead(&args, &data, now, hours));
+    let day = args.day.offset(now.date());
+    if let (Day::Weekday(weekday), true) = (args.day, day >= data.len()) {
+        return Err(anyhow!("There is no forecast for {} yet - only {} days of forecasts are available.", weekday_name(weekday), data.len()))
+    }
+    let available_days = data.len().saturating_sub(day);
+
+    let days: Vec<(raw::ForecastDay, Vec<u32>)> = match args.next {
+        Some(next) => {
+            let start = now.date().and_hms(now.hour(), 0, 0);
+            let slots: Vec<_> = (0..next).map(|i| start + Duration::hours(i as i64)).collect();
+            data.into_iter().filter_map(|day| {
+                let hours: Vec<u32> = slots.iter().filter(|slot| slot.date() == day.date).map(|slot| slot.hour()).collect();
+                if hours.is_empty() { None } else { Some((day, hours)) }
+            }).collect()
+        },
+        None => data.into_iter().skip(day).take(args.count).map(|day| (day, args.hours())).collect()
+    };
+
+    let mut odata = Vec::new();
+    let timezone = days.first().map(|(day, _)| day.timezone.clone());
+    for (raw::ForecastDay { date, times: fs, tides, sea_temperature, timezone: day_timezone }, hours) in days {
+        let mixer = Mixer::new(fs).with_status_threshold(args.status_threshold()).with_nearest_status(args.nearest_status);
+        let mut times = Vec::new();
+        let mut averaged = Vec::new();
+        if args.average {
+            for (hour, end) in intervals(&hours) {
+                let Some((forecast, source)) = mixer.average(hour, end) else { continue };
+                times.push(TimeWrapper::new(NaiveTime::from_hms(hour, 0, 0), forecast));
+                averaged.push((hour, end, source));
+            }
+        } else if args.round_times {
+            for hour in hours {
+                let Some((time, forecast)) = mixer.nearest(NaiveTime::from_hms(hour, 0, 0)) else { continue };
+                if times.last().is_none_or(|last: &TimeWrapper| last.time != time) {
+                    times.push(TimeWrapper::new(time, forecast));
+                }
+            }
+        } else {
+            for hour in hours {
+                let time = NaiveTime::from_hms(hour, 0, 0);
+                let Some(forecast) = mixer.lerp(time) else { continue };
+                times.push(TimeWrapper::new(time, forecast));
+            }
+        }
+
+        if let Some(previous) = previous.as_ref().and_then(|previous| previous.iter().find(|day| day.date == date)) {
+            let mixer = Mixer::new(previous.times.clone()).with_status_threshold(args.status_threshold()).with_nearest_status(args.nearest_status);
+            for time in &mut times {
+                time.previous = if args.average {
+                    averaged.iter()
+                        .find(|(hour, _, _)| *hour == time.time.hour())
+                        .and_then(|(hour, end, _)| mixer.average(*hour, *end))
+                        .map(|(forecast, _)| forecast)
+                } else {
+                    mixer.lerp(time.time)
+                };
+            }
+        }
+
+        if args.mark_now && date == now.date() {
+            let time = NaiveTime::from_hms(now.hour(), now.minute(), 0);
+            if let Some(forecast) = mixer.lerp(time) {
+                let idx = times.partition_point(|t| t.time < time);
+                times.insert(idx, TimeWrapper { now: true, ..TimeWrapper::new(time, forecast) });
+            }
+        }
+        for time in &mut times {
+            time.datetime = Some(day_timezone.datetime(date, time.time));
+            time.gust_severity = time.forecast.wind_gust.and_then(|gust| advice::gust_severity(gust, &args.config.gusts, args.freedom_units));
+            if args.explain {
+                time.source = match averaged.iter().find(|(hour, _, _)| !time.now && *hour == time.time.hour()) {
+                    Some((_, _, source)) => Some(source.clone()),
+                    None => mixer.source(time.time)
+                };
+            }
+        }
+
+        let wet_hours = wet_hours(&mixer);
+        let summary = if args.summary { Some(DaySummary { wet_hours, summary_status: day_summary_status(&times) }) } else { None };
+        let snow = if args.snow { Some(summarise_snow(&mixer)) } else { None };
+        let sun_times = coordinates.and_then(|(latitude, longitude)| sun::sun_times(date, latitude, longitude));
+        let daylight = sun_times.map(|(sunrise, sunset)| sunset - sunrise);
+        let sun_advice = if args.sun_advice {
+            let hourly: Vec<_> = (0..24)
+                .map(|hour| NaiveTime::from_hms(hour, 0, 0))
+                .filter_map(|time| mixer.lerp(time).map(|forecast| (time, forecast)))
+                .collect();
+            // Sunrise and sunset are in the location's time zone, and clamped to the day if they fall outside it.
+            let local = |at: DateTime<Utc>| {
+                let local = at.with_timezone(&day_timezone.offset()).naive_local();
+                match local.date().cmp(&date) {
+                    std::cmp::Ordering::Less => NaiveTime::from_hms(0, 0, 0),
+                    std::cmp::Ordering::Equal => local.time(),
+                    std::cmp::Ordering::Greater => NaiveTime::from_hms(23, 59, 59)
+                }
+            };
+            let local_sun_times = sun_times.map(|(sunrise, sunset)| (local(sunrise), local(sunset)));
+            advice::sun_advice(&hourly, local_sun_times)
+        } else {
+            Vec::new()
+        };
+        let mut day = DayWrapper { date, summary, daylight, tides, sea_temperature, pressure_trend: pressure_trend(&times), sun_advice, advice: None, snow, wet_hours, times };
+        if args.advice {
+            day.advice = advice::clothing_advice(&day, args.freedom_units);
+        }
+        odata.push(day);
+    }
+
+    // Averaged rows stand for a whole interval rather than a forecast time, so aren't recorded.
+    if let (Some(path), false) = (&args.sqlite, args.average) {
+        history::record(path, &location, fetched_at, args.freedom_units, &odata)?;
+    }
+
+    let alerts = alert::check(&args.alert, &odata);
+    if args.notify {
+        let renotify_after = Duration::minutes(args.renotify_after.unwrap_or(180).into());
+        let unnotified = alert::unnotified(args.fetch_options().cache_dir.as_deref(), &location, alerts.clone(), renotify_after);
+        alert::notify(&location, &unnotified);
+    }
+
+    let best_day = if args.best_day {
+        advice::best_day(&odata, &args.config.best_day, args.freedom_units)
+    } else {
+        None
+    };
+
+    if let Some(min_precip) = args.min_precip {
+        for day in &mut odata {
+            day.times.retain(|time| time.forecast.precipitation.is_some_and(|p| p >= min_precip));
+        }
+    }
+
+    if args.reverse {
+        odata.reverse();
+    }
+
+    let truncated = args.next.is_none() && available_days < args.count;
+    let stale = Utc::now() - fetched_at > Duration::minutes(args.stale_after);
+    Ok(Report { location, provider, fetched_at, current, alerts, previous_fetched_at, stale, available_days, truncated, best_day, summary_text, timezone, data: odata })
+}
+
+fn serve_request(args: &Args, url: &str) -> Result<Option<Report>> {
+    let url = reqwest::Url::parse("http://localhost")?.join(url)?;
+    if url.path() != "/forecast" {
+        return Ok(None)
+    }
+
+    let mut args = Args { location: None, non_interactive: true, ..args.clone() };
+    for (key, value) in url.query_pairs() {
+        match &*key {
+            "location" => args.location = Some(value.into_owned()),
+            "day" => args.day = value.parse().context("invalid day")?,
+            "count" => args.count = value.parse().context("invalid count")?,
+            _ => ()
+        }
+    }
+
+    if args.location.is_none() {
+        return Err(anyhow!("A location must be given."))
+    }
+
+    cli_main(args).map(Some)
+}
+
+fn serve_main(args: Args, addr: &str) -> Result<()> {
+    let server = tiny_http::Server::http(addr).map_err(|err| anyhow!(err))?;
+    let header = tiny_http::Header::from_bytes("Content-Type", "application/json").ok().context("can't create header")?;
+
+    for request in server.incoming_requests() {
+        let (status, output) = match serve_request(&args, request.url()) {
+            Ok(Some(report)) => (200, data_output(&args, report)),
+            Ok(None) => (404, Output::Error { error: serde_error::Error::new(&*anyhow!("Not found.")) }),
+            Err(err) => (500, Output::Error { error: serde_error::Error::new(&*err) })
+        };
+
+        let response = tiny_http::Response::from_string(to_json(&args, &output)?)
+            .with_status_code(status)
+            .with_header(header.clone());
+        // A client that went away before the response was sent shouldn't stop the server.
+        if let Err(err) = request.respond(response) {
+            eprintln!("Warning: can't send response: {}", err);
+        }
+    }
+
+    Ok(())
+}
+
+fn format_output_failure(error: anyhow::Error) {
+    println!("Error: ");
+    for (i, err) in error.chain().enumerate() {
+        println!("  {}: {}", i, err);
+    }
+
+    let bt = error.backtrace();
+    match bt.status() {
+        std::backtrace::BacktraceStatus::Captured => {
+            println!("\nBacktrace:\n{}", bt);
+        },
+        _ => println!("\nNo backtrace captured.")
+    }
+}
+
+/// Joins the days into one for showing side by side, keeping only the times forecast on every day.
+fn compare_days(data: Vec<DayWrapper>) -> Option<DayWrapper> {
+    let date = data.first()?.date;
+    let shared: Vec<NaiveTime> = data.iter()
+        .flat_map(|day| day.times.iter().filter(|time| !time.now).map(|time| time.time))
+        .filter(|time| data.iter().all(|day| day.times.iter().any(|t| !t.now && t.time == *time)))
+        .collect();
+    let times = data.into_iter()
+        .flat_map(|day| day.times)
+        .filter(|time| !time.now && shared.contains(&time.time))
+        .collect();
+    Some(DayWrapper { date, summary: None, daylight: None, tides: Vec::new(), sea_temperature: None, pressure_trend: None, sun_advice: Vec::new(), advice: None, snow: None, wet_hours: 0, times })
+}
+
+/// The most common condition at the given times, preferring the earliest in case of a tie.
+fn dominant_condition(times: &[TimeWrapper]) -> Condition {
+    let conditions: Vec<Condition> = times.iter().map(|time| time.forecast.status.condition()).collect();
+    let count = |condition: &Condition| conditions.iter().filter(|c| *c == condition).count();
+    conditions.iter()
+        .rev()
+        .max_by_key(|condition| count(condition))
+        .copied()
+        .unwrap_or(Condition::Unknown)
+}
+
+/// Describes the hours from now until the given number of hours ahead, across midnight if need be.
+fn summarise_hours_ahead(args: &Args, data: &[raw::ForecastDay], now: NaiveDateTime, hours: u32) -> Option<String> {
+    let start = now.date().and_hms(now.hour(), 0, 0);
+    let mixers: Vec<(NaiveDate, Mixer)> = data.iter()
+        .map(|day| (day.date, Mixer::new(day.times.clone()).with_status_threshold(args.status_threshold()).with_nearest_status(args.nearest_status)))
+        .collect();
+    let series: Vec<(NaiveTime, Forecast)> = (0..=hours)
+        .map(|i| start + Duration::hours(i as i64))
+        .filter_map(|slot| {
+            let (_, mixer) = mixers.iter().find(|(date, _)| *date == slot.date())?;
+            mixer.lerp(slot.time()).map(|forecast| (slot.time(), forecast))
+        })
+        .collect();
+    if series.is_empty() {
+        return None
+    }
+
+    let precision = args.precision.unwrap_or(1);
+    let format_temp = |t: f32| if args.freedom_units { format!("{:.*}f", precision, t) } else { format!("{:.*}C", precision, t) };
+    let format_speed = |s: f32| if args.freedom_units { format!("{:.*}mph", precision, s) } else { format!("{:.*}kph", precision, s) };
+
+    let mut parts = Vec::new();
+    let temperatures = || series.iter().filter_map(|(_, forecast)| forecast.temperature);
+    if let (Some(high), Some(low)) = (temperatures().reduce(f32::max), temperatures().reduce(f32::min)) {
+        parts.push(format!("high {}", format_temp(high)));
+        parts.push(format!("low {}", format_temp(low)));
+    }
+
+    let mut windows: Vec<(NaiveTime, NaiveTime)> = Vec::new();
+    let mut previous_wet = false;
+    for (time, forecast) in &series {
+        let wet = forecast.precipitation.is_some_and(|p| p >= 50.0);
+        match windows.last_mut() {
+            Some((_, end)) if wet && previous_wet => *end = *time,
+            _ if wet => windows.push((*time, *time)),
+            _ => ()
+        }
+        previous_wet = wet;
+    }
+    let dash = if args.ascii { "-" } else { "–" };
+    parts.push(if windows.is_empty() {
+        "staying dry".to_string()
+    } else {
+        let windows: Vec<String> = windows.iter().map(|(start, end)| if start == end {
+            format!("at {}", start.format("%H:%M"))
+        } else {
+            format!("{}{}{}", start.format("%H:%M"), dash, end.format("%H:%M"))
+        }).collect();
+        format!("rain likely {}", windows.join(" and "))
+    });
+
+    let gust = series.iter().filter_map(|(_, forecast)| forecast.wind_gust).fold(0.0, f32::max);
+    if advice::gust_severity(gust, &args.config.gusts, args.freedom_units).is_some() {
+        parts.push(format!("gusts up to {}", format_speed(gust)));
+    }
+
+    Some(format!("Next {} hours: {}", hours, parts.join(", ")))
+}
+
+fn format_visibility(visibility: f32, freedom_units: bool) -> String {
+    if freedom_units {
+        format!("{:.1}mi", visibility)
+    } else {
+        format!("{:.0}m", visibility)
+    }
+}
+
+/// How many time columns fit beside the row labels in a terminal of the given width,
+/// assuming each is as wide as the widest.
+fn fitting_columns(table: &Table, width: usize) -> usize {
+    let widths = table.column_max_content_widths();
+    let Some((label, times)) = widths.split_first() else { return 1 };
+    let widest = times.iter().copied().max().unwrap_or(0) as usize;
+    // Each column is padded by a space on either side, within the table's two borders.
+    let available = width.saturating_sub(*label as usize + 4);
+    (available / (widest + 2)).max(1)
+}
+
+/// Splits a table into several with at most the given number of columns after the row labels.
+fn paginate_table(table: &mut Table, columns: usize) -> Vec<Table> {
+    let preset = table.current_style_as_preset();
+    let mut rows: Vec<Row> = table.get_header().cloned().into_iter().collect();
+    rows.extend(table.row_iter().cloned());
+    let count = rows.iter().map(|row| row.cell_count()).max().unwrap_or(0).saturating_sub(1);
+
+    (0..count).step_by(columns.max(1)).map(|start| {
+        let mut page = Table::new();
+        page.load_preset(&preset).set_content_arrangement(comfy_table::ContentArrangement::Dynamic);
+        for (i, row) in rows.iter().enumerate() {
+            let mut cells = row.cell_iter().cloned();
+            let mut paged = Row::new();
+            if let Some(label) = cells.next() {
+                paged.add_cell(label);
+            }
+            for cell in cells.skip(start).take(columns.max(1)) {
+                paged.add_cell(cell);
+            }
+            if i == 0 {
+                page.set_header(paged);
+            } else {
+                page.add_row(paged);
+            }
+        }
+        page
+    }).collect()
+}
+
+fn status_icon(status: &WeatherStatus, emoji: bool, ascii: bool, language: Language) -> Cell {
+    let cell = Cell::new(if emoji { status.icon() } else { status.short_code_in(language.code()) });
+    if ascii {
+        return cell
+    }
+
+    let color = match status.condition() {
+        Condition::Clear => comfy_table::Color::Yellow,
+        Condition::ClearNight => comfy_table::Color::DarkBlue,
+        Condition::PartlyCloudy => comfy_table::Color::DarkYellow,
+        Condition::Cloudy => comfy_table::Color::Grey,
+        Condition::Rain => comfy_table::Color::Blue,
+        Condition::Snow => comfy_table::Color::White,
+        Condition::Thunder => comfy_table::Color::Magenta,
+        Condition::Unknown => return cell.add_attribute(comfy_table::Attribute::Bold)
+    };
+    cell.fg(color).add_attribute(comfy_table::Attribute::Bold)
+}
+
+/// Draws a chance of precipitation as a bar five characters wide, using eighth blocks in UTF8.
+fn precip_bar(precipitation: f32, ascii: bool) -> Cell {
+    const WIDTH: usize = 5;
+    let precipitation = precipitation.clamp(0.0, 100.0);
+
+    if ascii {
+        let filled = (precipitation / 100.0 * WIDTH as f32).round() as usize;
+        return Cell::new(format!("{:<width$}", "#".repeat(filled), width = WIDTH))
+    }
+
+    const EIGHTHS: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+    let eighths = (precipitation / 100.0 * (WIDTH * 8) as f32).round() as usize;
+    let bar = format!("{}{}", "█".repeat(eighths / 8), EIGHTHS[eighths % 8]);
+    let color = if precipitation < 33.0 {
+        comfy_table::Color::Green
+    } else if precipitation < 66.0 {
+        comfy_table::Color::Cyan
+    } else {
+        comfy_table::Color::Blue
+    };
+    Cell::new(format!("{:<width$}", bar, width = WIDTH)).fg(color)
+}
+
+fn format_output_success(args: Args, report: Report) {
+    if report.truncated {
+        match report.available_days {
+            0 => eprintln!("Note: no days of forecasts are available from that day"),
+            1 => eprintln!("Note: only 1 day of forecasts is available"),
+            n => eprintln!("Note: only {} days of forecasts are available", n)
+        }
+    }
+
+    let Report { location, fetched_at, alerts, previous_fetched_at, stale, best_day, summary_text, timezone, data, .. } = report;
+    if !args.no_header {
+        let timezone = timezone.map(|timezone| format!(", times in {}", timezone)).unwrap_or_default();
+        match args.location.as_deref().and_then(raw::parse_coordinates) {
+            Some((latitude, longitude)) => println!("Forecast for {},{} (near {}){}", latitude, longitude, location.name, timezone),
+            None if args.location.as_deref().and_then(what3words::parse_address).is_some() => {
+                println!("Forecast for {} (near {}){}", args.location.as_deref().unwrap_or_default().trim(), location.name, timezone)
+            },
+            None => println!("Forecast for {} ({}){}", location.name, location.area.as_deref().unwrap_or("N/A"), timezone)
+        }
+    }
+
+    if args.offline {
+        println!("Using cached forecast from {}", fetched_at.with_timezone(&Local).format("%e %B %Y %H:%M"));
+    }
+
+    if stale {
+        let warning = format!("{} forecast is {} old", if args.ascii { "Warning:" } else { "⚠" }, format_age(Utc::now() - fetched_at));
+        println!("{}", console::style(warning).yellow());
+    }
+
+    for alert in alerts {
+        println!("Alert: {}", alert);
+    }
+
+    if let Some(summary_text) = summary_text {
+        println!("{}", summary_text);
+    }
+
+    if args.diff {
+        let changed = data.iter().flat_map(|day| &day.times).any(|time| match &time.previous {
+            Some(previous) => temperature_changed(&time.forecast, previous) || precipitation_changed(&time.forecast, previous),
+            None => false
+        });
+
+        match previous_fetched_at {
+            Some(previous_fetched_at) if changed => println!("Compared with forecast from {}", previous_fetched_at.with_timezone(&Local).format("%e %B %Y %H:%M")),
+            Some(previous_fetched_at) => println!("No change since forecast from {}", previous_fetched_at.with_timezone(&Local).format("%e %B %Y %H:%M")),
+            None => println!("No previous forecast to compare with")
+        }
+    }
+
+    let (rise, fall) = if args.ascii { (" ^", " v") } else { (" ↑", " ↓") };
+    let trend = |now: Option<f32>, before: Option<f32>| if now > before { rise } else { fall };
+    let decimal = |s: String| if args.decimal_comma { s.replace('.', ",") } else { s };
+    let or_missing = |value: Option<String>| value.map(decimal).unwrap_or_else(|| "-".to_string());
+
+    let precision = args.precision.unwrap_or(1);
+    let format_temp = |t: f32| match (args.freedom_units, args.both_units) {
+        (true, false) => format!("{:.*}f", precision, t),
+        (false, false) => format!("{:.*}C", precision, t),
+        (true, true) => format!("{:.*}f / {:.*}C", precision, t, precision, other_temperature(t, true)),
+        (false, true) => format!("{:.*}C / {:.*}f", precision, t, precision, other_temperature(t, false))
+    };
+
+    let format_speed = |t: f32| match (args.freedom_units, args.both_units) {
+        (true, false) => format!("{:.*}mph", precision, t),
+        (false, false) => format!("{:.*}kph", precision, t),
+        (true, true) => format!("{:.*}mph / {:.*}kph", precision, t, precision, other_speed(t, true)),
+        (false, true) => format!("{:.*}kph / {:.*}mph", precision, t, precision, other_speed(t, false))
+    };
+
+    if data.is_empty() {
+        println!("No applicable data available.");
+    }
+
+    if let Some(best_day) = best_day {
+        println!(
+            "Best day: {} ({:.0}% precipitation, {}, {} wind, UV {:.0})",
+            best_day.date.format("%A %e %B"), best_day.precipitation, format_temp(best_day.temperature),
+            format_speed(best_day.wind_speed), best_day.uv_index
+        );
+    }
+
+    let format_duration = |d: Duration| format!("{}h {}m", d.num_hours(), d.num_minutes() % 60);
+
+    let data = if args.compare_days && data.len() > 1 {
+        let dates: Vec<String> = data.iter().map(|day| day.date.format("%a %-d %B").to_string()).collect();
+        println!("Comparing {}", dates.join(", "));
+        compare_days(data).into_iter().collect()
+    } else {
+        data
+    };
+
+    let mut previous: Option<(NaiveDate, Option<Duration>)> = None;
+    for DayWrapper { date, summary, daylight, tides, sea_temperature, pressure_trend, sun_advice, advice, snow, times: data, .. } in data {
+        let mut table = Table::new();
+        let mut times = Row::new();
+        let mut icons = Row::new();
+        let mut status = Row::new();
+        let mut precip = Row::new();
+        let mut bars = Row::new();
+        let mut temp = Row::new();
+        let mut feels = Row::new();
+        let mut wind = Row::new();
+        let mut dir = Row::new();
+        let mut gust = Row::new();
+        let mut visib = Row::new();
+        let mut humid = Row::new();
+        let mut uv = Row::new();
+        let mut pressure = Row::new();
+
+        times.add_cell(Cell::new("Time"));
+        icons.add_cell(Cell::new(""));
+        status.add_cell(Cell::new("Status"));
+        precip.add_cell(Cell::new("Precipitation"));
+        bars.add_cell(Cell::new(""));
+        temp.add_cell(Cell::new("Temperature"));
+        feels.add_cell(Cell::new("Feels Like"));
+        wind.add_cell(Cell::new("Wind Speed"));
+        dir.add_cell(Cell::new("Wind Direction"));
+        gust.add_cell(Cell::new("Wind Gust"));
+        visib.add_cell(Cell::new("Visibility"));
+        humid.add_cell(Cell::new("Humidity"));
+        uv.add_cell(Cell::new("UV Index"));
+        pressure.add_cell(Cell::new(match (pressure_trend, args.ascii) {
+            (Some(PressureTrend::Rising), false) => "Pressure ↑",
+            (Some(PressureTrend::Steady), false) => "Pressure →",
+            (Some(PressureTrend::Falling), false) => "Pressure ↓",
+            (Some(PressureTrend::Rising), true) => "Pressure ^",
+            (Some(PressureTrend::Steady), true) => "Pressure -",
+            (Some(PressureTrend::Falling), true) => "Pressure v",
+            (None, _) => "Pressure"
+        }));
+        let has_pressure = data.iter().any(|time| time.forecast.pressure.is_some());
+        let dry = args.min_precip.is_some() && data.is_empty();
+
+        let sources: Vec<String> = data.iter().filter_map(|time| match &time.source {
+            Some(Source::Interpolated { from, to, weight }) => Some(format!(
+                "{} is {:.0}% {} and {:.0}% {}",
+                time.time.format("%H:%M"), (1.0 - weight) * 100.0, from.format("%H:%M"), weight * 100.0, to.format("%H:%M")
+            )),
+            _ => None
+        }).collect();
+        let dash = if args.ascii { "-" } else { "–" };
+        let averages: Vec<String> = data.iter().filter_map(|time| match &time.source {
+            Some(Source::Averaged { from, to, samples }) => Some(format!("{} is {}{}{} ({} samples)", time.time.format("%H:%M"), from.format("%H:%M"), dash, to.format("%H:%M"), samples)),
+            _ => None
+        }).collect();
+
+        let dominant = dominant_condition(&data);
+
+        let severe_gust = data.iter()
+            .filter(|time| time.gust_severity == Some(GustSeverity::Red))
+            .filter_map(|time| time.forecast.wind_gust)
+            .reduce(f32::max);
+
+        let mut last_time: Option<NaiveTime> = None;
+        let mut last_date: Option<NaiveDate> = None;
+        for TimeWrapper { time, datetime, now, feels_like_delta, gust_severity, forecast, previous, .. } in data {
+            let time_date = datetime.map(|datetime| datetime.date().naive_local());
+            let new_date = args.compare_days && last_date.is_some() && time_date != last_date;
+            if new_date || args.around.is_some() && !now && last_time.is_some_and(|last| time - last > Duration::hours(1)) {
+                for row in [&mut times, &mut icons, &mut status, &mut precip, &mut bars, &mut temp, &mut feels, &mut wind, &mut dir, &mut gust, &mut visib, &mut humid, &mut uv, &mut pressure] {
+                    row.add_cell(Cell::new(""));
+                }
+            }
+            if !now {
+                last_time = Some(time);
+            }
+            last_date = time_date;
+
+            times.add_cell(match (now, args.ascii) {
+                (false, _) if args.compare_days => Cell::new(match time_date {
+                    Some(date) => format!("{} {}", date.format("%a"), time.format("%H:%M")),
+                    None => time.format("%H:%M").to_string()
+                }),
+                (false, _) => Cell::new(time.format("%H:%M")),
+                (true, true) => Cell::new(format!("> {}", time.format("%H:%M"))),
+                (true, false) => Cell::new(format!("▶ {}", time.format("%H:%M"))).add_attribute(comfy_table::Attribute::Bold)
+            });
+            icons.add_cell(status_icon(&forecast.status, args.emoji, args.ascii, args.language));
+            status.add_cell(Cell::new(if args.emoji { forecast.status.icon() } else { forecast.status.short_code_in(args.language.code()) }));
+            let (precip_change, temp_change) = match &previous {
+                Some(previous) => (
+                    if previous.precipitation.is_some_and(|p| p < 50.0) && forecast.precipitation.is_some_and(|p| p >= 50.0) {
+                        " (new)"
+                    } else if precipitation_changed(&forecast, previous) {
+                        trend(forecast.precipitation, previous.precipitation)
+                    } else {
+                        ""
+                    },
+                    if temperature_changed(&forecast, previous) { trend(forecast.temperature, previous.temperature) } else { "" }
+                ),
+                None => ("", "")
+            };
+            precip.add_cell(Cell::new(or_missing(forecast.precipitation.map(|p| format!("{:.0}%{}", p, precip_change)))));
+            bars.add_cell(forecast.precipitation.map(|p| precip_bar(p, args.ascii)).unwrap_or_else(|| Cell::new("")));
+            temp.add_cell(Cell::new(or_missing(forecast.temperature.map(|t| format!("{}{}", format_temp(t), temp_change)))));
+            match (forecast.feels_like, feels_like_delta) {
+                (Some(feels_like), Some(delta)) if feels_like_diverges(&forecast, args.freedom_units) => {
+                    feels.add_cell(Cell::new(decimal(format!("{} ({:+.*})", format_temp(feels_like), precision, delta))))
+                },
+                (feels_like, _) => feels.add_cell(Cell::new(or_missing(feels_like.map(format_temp))))
+            };
+            wind.add_cell(Cell::new(or_missing(forecast.wind_speed.map(format_speed))));
+            dir.add_cell(Cell::new(match forecast.wind_direction {
+                Some(direction) if args.wind && !args.ascii => {
+                    let arrow = wind_arrow(&direction).map(|arrow| format!("{} ", arrow)).unwrap_or_default();
+                    format!("{}{}", arrow, direction)
+                },
+                direction => or_missing(direction)
+            }));
+            let gust_cell = Cell::new(or_missing(forecast.wind_gust.map(format_speed)));
+            gust.add_cell(match (gust_severity, args.ascii) {
+                (Some(GustSeverity::Amber), false) => gust_cell.fg(comfy_table::Color::Yellow),
+                (Some(GustSeverity::Red), false) => gust_cell.fg(comfy_table::Color::Red),
+                _ => gust_cell
+            });
+            visib.add_cell(Cell::new(or_missing(forecast.visibility.map(|v| format_visibility(v, args.freedom_units)))));
+            humid.add_cell(Cell::new(or_missing(forecast.humidity.map(|h| format!("{:.0}%", h)))));
+            uv.add_cell(Cell::new(or_missing(forecast.uv_index.map(|uv| format!("{:.1}", uv)))));
+            pressure.add_cell(Cell::new(or_missing(forecast.pressure.map(|p| args.pressure_unit().format(p)))));
+        }
+
+        if args.ascii {
+            table.load_preset(comfy_table::presets::ASCII_BORDERS_ONLY_CONDENSED);
+        } else {
+            table.load_preset(comfy_table::presets::UTF8_BORDERS_ONLY)
+                .apply_modifier(comfy_table::modifiers::UTF8_ROUND_CORNERS);
+        }
+
+        table.set_content_arrangement(comfy_table::ContentArrangement::Dynamic)
+            .set_header(times);
+
+        if args.summary_icons {
+            table.add_row(icons);
+        }
+
+        for column in args.columns() {
+            let row = match column {
+                Column::Status => &mut status,
+                Column::Precip => &mut precip,
+                Column::Temp => &mut temp,
+                Column::Feels => &mut feels,
+                Column::Wind => &mut wind,
+                Column::Dir => &mut dir,
+                Column::Gust => &mut gust,
+                Column::Visibility => &mut visib,
+                Column::Humidity => &mut humid,
+                Column::Uv => &mut uv,
+                Column::Pressure if has_pressure => &mut pressure,
+                Column::Pressure => continue
+            };
+            table.add_row(std::mem::take(row));
+
+            if column == Column::Precip && args.precip_bars {
+                table.add_row(std::mem::take(&mut bars));
+            }
+        }
+
+        if args.group_weeks && previous.is_none_or(|(pdate, _)| week_of(pdate) != week_of(date)) {
+            println!("Week of {}", week_of(date).format("%-d %B"));
+        }
+
+        match (daylight, previous) {
+            _ if args.no_dates || args.compare_days => (),
+            (Some(daylight), Some((pdate, Some(pdaylight)))) if pdate.succ() == date => {
+                let delta = (daylight - pdaylight).num_minutes();
+                println!("{} (daylight {}, {:+}m vs yesterday)", date.format("%e %B %Y"), format_duration(daylight), delta);
+            },
+            (Some(daylight), _) => println!("{} (daylight {})", date.format("%e %B %Y"), format_duration(daylight)),
+            (None, _) => println!("{}", date.format("%e %B %Y"))
+        }
+        previous = Some((date, daylight));
+
+        if args.art {
+            for line in dominant.art(args.ascii, args.emoji) {
+                println!("{}", line);
+            }
+        }
+
+        if let Some(summary) = summary {
+            let mut parts = Vec::new();
+            let mut status = summary.summary_status.chars();
+            if let Some(first) = status.next() {
+                parts.push(first.to_uppercase().chain(status).collect());
+            }
+
+            parts.push(match summary.wet_hours {
+                0 => "No wet hours".to_string(),
+                1 => "1 wet hour".to_string(),
+                n => format!("{} wet hours", n)
+            });
+
+            if pressure_trend == Some(PressureTrend::Falling) {
+                parts.push("Pressure falling".to_string());
+            }
+
+            if let Some(severe_gust) = severe_gust {
+                parts.push(format!("Severe gusts up to {}", format_speed(severe_gust)));
+            }
+
+            println!("{}", parts.join(", "));
+        }
+
+        if let Some(snow) = snow {
+            let hours: Vec<String> = snow.hours.iter().map(|time| time.format("%H:%M").to_string()).collect();
+            let total = snow.total_amount.map(|amount| if args.freedom_units {
+                format!(" ({:.1}in expected)", amount)
+            } else {
+                format!(" ({:.1}cm expected)", amount)
+            }).unwrap_or_default();
+
+            if hours.is_empty() {
+                println!("No snow likely{}", total);
+            } else {
+                println!("Snow likely at {}{}", hours.join(", "), total);
+            }
+        }
+
+        if !tides.is_empty() {
+            let tides: Vec<String> = tides.iter().map(|tide| format!("{} {}", tide.time.format("%H:%M"), match tide.kind {
+                TideKind::High => "H",
+                TideKind::Low => "L"
+            })).collect();
+            println!("Tides: {}", tides.join(", "));
+        }
+
+        if let Some(sea_temperature) = sea_temperature {
+            println!("Sea temperature: {}", format_temp(sea_temperature));
+        }
+
+        for advice in sun_advice {
+            println!("Sun: {}", advice);
+        }
+
+        if args.explain {
+            if !averages.is_empty() {
+                println!("Averaged: {}", averages.join(", "));
+            }
+            if !sources.is_empty() {
+                println!("Interpolated: {}", sources.join(", "));
+            } else if averages.is_empty() {
+                println!("All times are exact forecasts");
+            }
+        }
+
+        if let Some(advice) = advice {
+            println!("Advice: {}", advice.text);
+        }
+        let stdout = console::Term::stdout();
+        let max_columns = args.max_columns.or_else(|| {
+            let (_, width) = stdout.size_checked().filter(|_| stdout.is_term())?;
+            Some(fitting_columns(&table, width as usize))
+        });
+
+        if dry {
+            println!("Dry all day");
+        } else if let Some(max_columns) = max_columns.filter(|max| table.column_iter().count() > max + 1) {
+            for page in paginate_table(&mut table, max_columns) {
+                println!("{}", page);
+            }
+        } else {
+            println!("{}", table);
+        }
+    }
+}
+
+fn format_accuracy_success(args: Args, location: Location, accuracy: history::Accuracy) {
+    println!("Forecast accuracy for {} ({})", location.name, location.area.as_deref().unwrap_or("N/A"));
+    println!("Forecasts compared: {}", accuracy.compared);
+
+    if let Some(error) = accuracy.mean_temperature_error {
+        println!("Mean temperature error: {:.1}{}", error, if args.freedom_units { "f" } else { "C" });
+    }
+
+    if let Some(rate) = accuracy.precipitation_hit_rate {
+        println!("Precipitation hit rate: {:.0}%", rate * 100.0);
+    }
+}
+
+fn format_prefetch_success(prefetched: Vec<Prefetched>) {
+    let cached = prefetched.iter().filter(|p| p.error.is_none()).count();
+    println!("Cached forecasts for {} of {} locations", cached, prefetched.len());
+
+    for Prefetched { query, error, .. } in prefetched {
+        if let Some(error) = error {
+            println!("  Could not fetch {}: {}", query, error);
+        }
+    }
+}
+
+fn format_age(age: Duration) -> String {
+    if age.num_days() > 0 {
+        format!("{}d", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}m", age.num_minutes())
+    }
+}
+
+fn data_output(args: &Args, report: Report) -> Output {
+    let pressure_unit = args.pressure_unit();
+    let Report { location, provider, fetched_at, alerts, stale, truncated, best_day, summary_text, timezone, mut data, .. } = report.with_pressures_in(pressure_unit);
+    for time in data.iter_mut().flat_map(|day| &mut day.times) {
+        if args.both_units {
+            time.other_units = Some(OtherUnits::new(&time.forecast, args.freedom_units));
+        }
+    }
+
+    let (temperature_unit, speed_unit, visibility_unit) = unit_names(args);
+    let meta = Meta { provider, temperature_unit, speed_unit, visibility_unit, pressure_unit, timezone };
+    if args.json_summary {
+        let data = data.iter().filter_map(aggregate_day).collect();
+        return Output::Summary { location, fetched_at, stale, alerts, truncated, meta, data }
+    }
+    if args.wind {
+        let data = data.into_iter().map(wind_day).collect();
+        return Output::Wind { location, fetched_at, stale, truncated, meta, data }
+    }
+    if args.group_weeks {
+        let mut weeks: Vec<Week> = Vec::new();
+        for day in data {
+            match weeks.last_mut() {
+                Some(week) if week.week_of == week_of(day.date) => week.days.push(day),
+                _ => weeks.push(Week { week_of: week_of(day.date), days: vec![day] })
+            }
+        }
+        return Output::Weeks { location, fetched_at, stale, alerts, truncated, best_day, summary_text, meta, weeks }
+    }
+    Output::Data { location, fetched_at, stale, alerts, truncated, best_day, summary_text, meta, data }
+}
+
+fn unit_names(args: &Args) -> (&'static str, &'static str, &'static str) {
+    if args.freedom_units {
+        ("fahrenheit", "mph", "miles")
+    } else {
+        ("celsius", "kph", "metres")
+    }
+}
+
+fn to_json(args: &Args, output: &Output) -> serde_json::Result<String> {
+    use serde_json::Value;
+
+    if !args.json_units {
+        return serde_json::to_string(output)
+    }
+
+    let mut value = serde_json::to_value(output)?;
+    let (temperature_unit, speed_unit, visibility_unit) = unit_names(args);
+    let fields = [("temperature", temperature_unit), ("feels_like", temperature_unit), ("wind_speed", speed_unit), ("wind_gust", speed_unit), ("visibility", visibility_unit)];
+    let days: Vec<&mut Value> = match value.get_mut("weeks").and_then(Value::as_array_mut) {
+        Some(weeks) => weeks.iter_mut().filter_map(|week| week.get_mut("days")?.as_array_mut()).flatten().collect(),
+        None => value.get_mut("data").and_then(Value::as_array_mut).into_iter().flatten().collect()
+    };
+    if args.json_summary {
+        for day in days.into_iter().filter_map(Value::as_object_mut) {
+            for field in ["min_temperature", "max_temperature", "mean_temperature"] {
+                if let Some(value) = day.get_mut(field) {
+                    *value = serde_json::json!({ "value": value.take(), "unit": temperature_unit });
+                }
+            }
+        }
+        return Ok(value.to_string())
+    }
+    for time in days.into_iter().filter_map(|day| day.get_mut("times")?.as_array_mut()).flatten() {
+        // Wind output has the fields on each time, rather than in its forecast.
+        if args.wind {
+            for (field, unit) in fields {
+                if let Some(value) = time.get_mut(field) {
+                    *value = serde_json::json!({ "value": value.take(), "unit": unit });
+                }
+            }
+            continue
+        }
+        for key in ["forecast", "previous"] {
+            let Some(forecast) = time.get_mut(key).and_then(Value::as_object_mut) else { continue };
+            for (field, unit) in fields {
+                if let Some(value) = forecast.get_mut(field) {
+                    *value = serde_json::json!({ "value": value.take(), "unit": unit });
+                }
+            }
+        }
+    }
+
+    Ok(value.to_string())
+}
+
+/// The schema of JSON output, with the fields that `to_json` gives units as value and unit objects.
+fn json_schema(args: &Args) -> serde_json::Result<String> {
+    let schema = schemars::schema_for!(Output);
+    if !args.json_units {
+        return serde_json::to_string_pretty(&schema)
+    }
+
+    let mut schema = serde_json::to_value(schema)?;
+    let (temperature_unit, speed_unit, visibility_unit) = unit_names(args);
+    let fields = [
+        ("Forecast", "temperature", temperature_unit), ("Forecast", "feels_like", temperature_unit), ("Forecast", "wind_speed", speed_unit),
+        ("Forecast", "wind_gust", speed_unit), ("Forecast", "visibility", visibility_unit), ("WindTime", "wind_speed", speed_unit),
+        ("WindTime", "wind_gust", speed_unit), ("DayAggregate", "min_temperature", temperature_unit),
+        ("DayAggregate", "max_temperature", temperature_unit), ("DayAggregate", "mean_temperature", temperature_unit)
+    ];
+    for (definition, field, unit) in fields {
+        let Some(property) = schema.pointer_mut(&format!("/definitions/{}/properties/{}", definition, field)) else { continue };
+        *property = serde_json::json!({
+            "type": "object",
+            "required": ["value", "unit"],
+            "properties": { "value": property.take(), "unit": { "type": "string", "enum": [unit] } }
+        });
+    }
+    serde_json::to_string_pretty(&schema)
+}
+
+fn format_json_success(args: &Args, report: Report) {
+    print!("{}", to_json(args, &data_output(args, report)).unwrap());
+}
+
+fn format_template_success(path: &std::path::Path, pressure_unit: PressureUnit, report: Report) -> Result<()> {
+    let template = template::Template::load(path)?;
+    let Report { location, fetched_at, data, .. } = report.with_pressures_in(pressure_unit);
+    for day in &data {
+        let context = serde_json::json!({ "location": location, "fetched_at": fetched_at, "pressure_unit": pressure_unit, "day": day });
+        print!("{}", template.render(&context));
+    }
+    Ok(())
+}
+
+fn format_flat_json_success(pressure_unit: PressureUnit, report: Report) {
+    let Report { location, data, .. } = report.with_pressures_in(pressure_unit);
+    let location = &location;
+    let records: Vec<FlatRecord> = data.iter()
+        .flat_map(|day| day.times.iter().map(move |TimeWrapper { time, datetime, forecast, .. }| {
+            FlatRecord { location, date: day.date, time: *time, datetime: *datetime, forecast, pressure_unit }
+        }))
+        .collect();
+    println!("{}", serde_json::to_string(&records).unwrap());
+}
+
+fn format_ndjson_success(pressure_unit: PressureUnit, report: Report) {
+    let Report { location, data, .. } = report.with_pressures_in(pressure_unit);
+    for day in &data {
+        for TimeWrapper { time, datetime, forecast, .. } in &day.times {
+            let record = FlatRecord { location: &location, date: day.date, time: *time, datetime: *datetime, forecast, pressure_unit };
+            println!("{}", serde_json::to_string(&record).unwrap());
+        }
+    }
+}
+
+fn parse_max_columns(s: &str) -> Result<usize> {
+    match s.parse()? {
+        0 => Err(anyhow!("expected at least one column")),
+        columns => Ok(columns)
+    }
+}
+
+fn parse_precision(s: &str) -> Result<usize> {
+    match s.parse()? {
+        precision @ 0..=3 => Ok(precision),
+        _ => Err(anyhow!("expected between 0 and 3 decimal places"))
+    }
+}
+
+fn parse_delimiter(s: &str) -> Result<char> {
+    match s {
+        "tab" | "\\t" => Ok('\t'),
+        s => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii() && c != '"' && c != '\n' => Ok(c),
+                _ => Err(anyhow!("expected a single ASCII character"))
+            }
+        }
+    }
+}
+
+/// Writes a number to three decimal places at most, which leaves out the noise that unit conversions
+/// leave in the last digits of an f32, such as 10.799999 for 10.8.
+fn csv_number(value: f32) -> String {
+    let rounded = format!("{:.3}", value);
+    match rounded.trim_end_matches('0').trim_end_matches('.') {
+        "-0" => "0".to_string(),
+        number => number.to_string()
+    }
+}
+
+const CSV_COLUMNS: &[&str] = &[
+    "name", "area", "date", "time", "status", "precipitation", "temperature", "feels_like", "wind_speed",
+    "wind_direction", "wind_gust", "visibility", "humidity", "uv_index", "pressure", "snow_probability", "snow_amount", "pressure_unit"
+];
+
+fn format_csv_success(args: &Args, report: Report) {
+    print!("{}", to_csv(args, report));
+}
+
+fn to_csv(args: &Args, report: Report) -> String {
+    let pressure_unit = args.pressure_unit();
+    let Report { location, data, .. } = report.with_pressures_in(pressure_unit);
+    let delimiter = args.csv_delimiter.unwrap_or(if args.decimal_comma { ';' } else { ',' });
+    let quote = |s: &str| if s.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    };
+    let mut out = String::new();
+    if args.excel {
+        out.push('\u{FEFF}');
+    }
+
+    out.push_str(&CSV_COLUMNS.join(&delimiter.to_string()));
+    out.push_str("\r\n");
+
+    let optional = |value: Option<f32>| match value {
+        Some(value) if args.decimal_comma => csv_number(value).replace('.', ","),
+        Some(value) => csv_number(value),
+        None => String::new()
+    };
+    let rounded = |value: Option<f32>| match (value, args.precision) {
+        (Some(value), Some(precision)) if args.decimal_comma => format!("{:.*}", precision, value).replace('.', ","),
+        (Some(value), Some(precision)) => format!("{:.*}", precision, value),
+        (value, _) => optional(value)
+    };
+    for day in &data {
+        for TimeWrapper { time, forecast: f, .. } in &day.times {
+            let row = [
+                location.name.clone(), location.area.clone().unwrap_or_default(), day.date.to_string(), time.format("%H:%M").to_string(),
+                f.status.label().to_string(), optional(f.precipitation), rounded(f.temperature), rounded(f.feels_like),
+                rounded(f.wind_speed), f.wind_direction.clone().unwrap_or_default(), rounded(f.wind_gust), optional(f.visibility),
+                optional(f.humidity), optional(f.uv_index), optional(f.pressure), optional(f.snow_probability), optional(f.snow_amount),
+                pressure_unit.name().to_string()
+            ];
+            let row: Vec<String> = row.iter().map(|field| quote(field)).collect();
+            out.push_str(&row.join(&delimiter.to_string()));
+            out.push_str("\r\n");
+        }
+    }
+
+    out
+}
+
+fn format_prometheus_success(args: Args, report: Report) -> Result<()> {
+    let pressure_unit = args.pressure_unit();
+    let Report { location, current, .. } = report.with_pressures_in(pressure_unit);
+    let TimeWrapper { forecast, .. } = current.context("There is no forecast available for the current hour.")?;
+
+    let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    let labels = format!("location=\"{}\",geohash=\"{}\"", escape(&location.name), escape(location.geohash.as_deref().unwrap_or("")));
+    let (temp_unit, speed_unit) = if args.freedom_units { ("fahrenheit", "mph") } else { ("celsius", "kph") };
+
+    let metrics = [
+        (format!("weather_temperature_{}", temp_unit), "Forecast temperature", forecast.temperature),
+        (format!("weather_feels_like_{}", temp_unit), "Forecast perceived temperature", forecast.feels_like),
+        ("weather_precip_probability".to_string(), "Forecast chance of precipitation", forecast.precipitation.map(|p| p / 100.0)),
+        (format!("weather_wind_speed_{}", speed_unit), "Forecast wind speed", forecast.wind_speed),
+        (format!("weather_wind_gust_{}", speed_unit), "Forecast wind gust speed", forecast.wind_gust),
+        (if args.freedom_units { "weather_visibility_miles" } else { "weather_visibility_metres" }.to_string(), "Forecast visibility", forecast.visibility),
+        ("weather_humidity_ratio".to_string(), "Forecast relative humidity", forecast.humidity.map(|h| h / 100.0)),
+        ("weather_uv_index".to_string(), "Forecast UV index", forecast.uv_index),
+        ("weather_snow_probability".to_string(), "Estimated chance of snow", forecast.snow_probability.map(|p| p / 100.0)),
+        (format!("weather_pressure_{}", pressure_unit.name()), "Forecast pressure", forecast.pressure)
+    ];
+
+    for (name, help, value) in metrics.into_iter().filter_map(|(name, help, value)| Some((name, help, value?))) {
+        println!("# HELP {} {}", name, help);
+        println!("# TYPE {} gauge", name);
+        println!("{}{{{}}} {}", name, labels, value);
+    }
+
+    Ok(())
+}
+
+fn format_env_success(pressure_unit: PressureUnit, report: Report) -> Result<()> {
+    let Report { location, current, .. } = report.with_pressures_in(pressure_unit);
+    let TimeWrapper { time, forecast, .. } = current.context("There is no forecast available for the current hour.")?;
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "'\\''"));
+
+    println!("WEATHER_LOCATION={}", quote(&location.name));
+    println!("WEATHER_TIME={}", quote(&time.format("%H:%M").to_string()));
+    println!("WEATHER_PRESSURE_UNIT={}", quote(pressure_unit.name()));
+
+    if let serde_json::Value::Object(fields) = serde_json::to_value(&forecast)? {
+        for (key, value) in fields {
+            let value = match value {
+                serde_json::Value::String(s) => s,
+                serde_json::Value::Null => String::new(),
+                value => value.to_string()
+            };
+            println!("WEATHER_{}={}", key.to_ascii_uppercase(), quote(&value));
+        }
+    }
+
+    Ok(())
+}
+
+fn format_env_failure(error: anyhow::Error) {
+    eprintln!("Error: ");
+    for (i, err) in error.chain().enumerate() {
+        eprintln!("  {}: {}", i, err);
+    }
+}
+
+/// Keeps errors out of the scraped file, with the exit status showing that there was one.
+fn format_prometheus_failure(error: anyhow::Error) -> ! {
+    format_env_failure(error);
+    std::process::exit(1)
+}
+
+fn highlight_json(value: &serde_json::Value, indent: usize) -> String {
+    use serde_json::Value;
+    use console::style;
+
+    let pad = "  ".repeat(indent + 1);
+    match value {
+        Value::Null => style("null").magenta().to_string(),
+        Value::Bool(b) => style(b).magenta().to_string(),
+        Value::Number(n) => style(n).yellow().to_string(),
+        Value::String(_) => style(value).green().to_string(),
+        Value::Array(items) if items.is_empty() => "[]".to_string(),
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(|item| format!("{}{}", pad, highlight_json(item, indent + 1))).collect();
+            format!("[\n{}\n{}]", items.join(",\n"), "  ".repeat(indent))
+        },
+        Value::Object(fields) if fields.is_empty() => "{}".to_string(),
+        Value::Object(fields) => {
+            let fields: Vec<String> = fields.iter().map(|(key, value)| {
+                format!("{}{}: {}", pad, style(Value::String(key.clone())).cyan().bold(), highlight_json(value, indent + 1))
+            }).collect();
+            format!("{{\n{}\n{}}}", fields.join(",\n"), "  ".repeat(indent))
+        }
+    }
+}
+
+fn format_json_failure(args: &Args, err: anyhow::Error) {
+    let output = Output::Error { error: serde_error::Error::new(&*err) };
+    let stdout = console::Term::stdout();
+    if stdout.is_term() && console::colors_enabled() && !args.ascii {
+        let value = serde_json::to_value(&output).unwrap();
+        println!("{}", highlight_json(&value, 0));
+    } else {
+        serde_json::to_writer(std::io::stdout(), &output).unwrap();
+    }
+}
+
+fn main() {
+    let mut args = Args::parse();
+    args.emoji = !args.ascii && !args.no_emoji && emoji_supported();
+    if args.two_day {
+        args.day = Day::Offset(0);
+        args.count = 2;
+    } else if args.week {
+        args.count = 7;
+    }
+
+    args.config = match config::load() {
+        Ok(config) => config,
+        Err(err) => return if args.prometheus {
+            format_prometheus_failure(err)
+        } else if !args.json {
+            format_output_failure(err)
+        } else {
+            format_json_failure(&args, err)
+        }
+    };
+
+    if args.location.is_none() && !args.no_default {
+        args.location = std::env::var("WEATHER_DEFAULT_LOCATION").ok().filter(|location| !location.trim().is_empty());
+    }
+
+    if args.version_info {
+        println!("{}", serde_json::to_string_pretty(&version_info()).unwrap());
+        return
+    }
+
+    if args.clear_cache {
+        let dir = args.fetch_options().cache_dir;
+        match cache::clear(dir.as_deref()) {
+            Ok(removed) => if !args.json {
+                println!("Removed {} cached results", removed)
+            } else {
+                serde_json::to_writer(std::io::stdout(), &Output::ClearCache { removed }).unwrap()
+            },
+            Err(err) => if !args.json {
+                format_output_failure(err)
+            } else {
+                format_json_failure(&args, err)
+            }
+        }
+        return
+    }
+
+    if args.self_test {
+        let checks = self_test_main(&args);
+        let passed = checks.iter().all(|check| check.passed);
+        if !args.json {
+            format_self_test_success(&checks)
+        } else {
+            serde_json::to_writer(std::io::stdout(), &Output::SelfTest { passed, checks }).unwrap()
+        }
+
+        if !passed {
+            std::process::exit(1)
+        }
+        return
+    }
+
+    if args.json_schema {
+        println!("{}", json_schema(&args).unwrap());
+        return
+    }
+
+    if let Some(addr) = args.serve.clone() {
+        if let Err(err) = serve_main(args.clone(), &addr) {
+            if !args.json {
+                format_output_failure(err)
+            } else {
+                format_json_failure(&args, err)
+            }
+        }
+        return
+    }
+
+    if !args.prefetch.is_empty() {
+        match prefetch_main(args.clone()) {
+            Ok(prefetched) => if !args.json {
+                format_prefetch_success(prefetched)
+            } else {
+                serde_json::to_writer(std::io::stdout(), &Output::Prefetch { prefetched }).unwrap()
+            },
+            Err(err) => if !args.json {
+                format_output_failure(err)
+            } else {
+                format_json_failure(&args, err)
+            }
+        }
+        return
+    }
+
+    if args.dry_run {
+        match raw::dry_run(args.location.as_deref(), &args.search_options(), &args.fetch_options()) {
+            Ok(urls) => if !args.json {
+                for url in urls {
+                    println!("{}", url);
+                }
+            } else {
+                serde_json::to_writer(std::io::stdout(), &Output::DryRun { urls }).unwrap()
+            },
+            Err(err) => if !args.json {
+                format_output_failure(err)
+            } else {
+                format_json_failure(&args, err)
+            }
+        }
+        return
+    }
+
+    if args.search_only {
+        match search_main(&args) {
+            Ok((query, results)) => if !args.json {
+                for loc in results {
+                    println!("{} ({}) {}", loc.name, loc.area.as_deref().unwrap_or("N/A"), loc.geohash.as_deref().unwrap_or("-"));
+                }
+            } else {
+                serde_json::to_writer(std::io::stdout(), &Output::Locations { query, results }).unwrap()
+            },
+            Err(err) => if !args.json {
+                format_output_failure(err)
+            } else {
+                format_json_failure(&args, err)
+            }
+        }
+        return
+    }
+
+    if args.coords {
+        match coords_main(args.clone()) {
+            Ok((location, latitude, longitude)) => if !args.json {
+                println!("{},{}", latitude, longitude)
+            } else {
+                serde_json::to_writer(std::io::stdout(), &Output::Coordinates { location, latitude, longitude }).unwrap()
+            },
+            Err(err) => if !args.json {
+                format_output_failure(err)
+            } else {
+                format_json_failure(&args, err)
+            }
+        }
+        return
+    }
+
+    if args.accuracy {
+        match accuracy_main(args.clone()) {
+            Ok((location, accuracy)) => if !args.json {
+                format_accuracy_success(args, location, accuracy)
+            } else {
+                serde_json::to_writer(std::io::stdout(), &Output::Accuracy { location, accuracy }).unwrap()
+            },
+            Err(err) => if !args.json {
+                format_output_failure(err)
+            } else {
+                format_json_failure(&args, err)
+            }
+        }
+        return
+    }
+
+    match cli_main(args.clone()) {
+        Ok(report) => if args.env {
+            if let Err(err) = format_env_success(args.pressure_unit(), report) {
+                format_env_failure(err)
+            }
+        } else if args.prometheus {
+            if let Err(err) = format_prometheus_success(args, report) {
+                format_prometheus_failure(err)
+            }
+        } else if args.ndjson {
+            format_ndjson_success(args.pressure_unit(), report)
+        } else if args.flat_json {
+            format_flat_json_success(args.pressure_unit(), report)
+        } else if args.csv {
+            format_csv_success(&args, report)
+        } else if let Some(path) = &args.template {
+            if let Err(err) = format_template_success(path, args.pressure_unit(), report) {
+                format_output_failure(err)
+            }
+        } else if !args.json {
+            format_output_success(args, report)
+        } else {
+            format_json_success(&args, report)
+        },
+        Err(err) => if args.prometheus {
+            format_prometheus_failure(err)
+        } else if args.env || args.csv {
+            format_env_failure(err)
+        } else if !args.json && !args.ndjson && !args.flat_json {
+            format_output_failure(err)
+        } else {
+            format_json_failure(&args, err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(forecasts: Vec<(NaiveTime, Forecast)>) -> Report {
+        let date = NaiveDate::from_ymd(2026, 1, 14);
+        let day = DayWrapper {
+            date, summary: None, daylight: None, tides: Vec::new(), sea_temperature: None, pressure_trend: None,
+            sun_advice: Vec::new(), advice: None, snow: None, wet_hours: 0,
+            times: forecasts.into_iter().map(|(time, forecast)| TimeWrapper::new(time, forecast)).collect()
+        };
+        Report {
+            location: Location { name: "London".to_string(), area: Some("Greater London".to_string()), geohash: None, latitude: None, longitude: None },
+            provider: Backend::Metoffice, fetched_at: Utc::now(), current: None, alerts: Vec::new(), previous_fetched_at: None,
+            stale: false, available_days: 1, truncated: false, best_day: None, summary_text: None, timezone: None, data: vec![day]
+        }
+    }
+
+    #[test]
+    fn time_range_needs_a_step_and_count() {
+        assert!("9:0:3".parse::<TimeRange>().is_err());
+        assert!("9:3:0".parse::<TimeRange>().is_err());
+        assert!("9:3:6".parse::<TimeRange>().is_err());
+        assert_eq!("9:3:5".parse::<TimeRange>().unwrap().hours(), [9, 12, 15, 18, 21]);
+    }
+
+    #[test]
+    fn mixer_keeps_the_last_of_duplicate_times() {
+        let at = |hour: u32, temperature: f32| (NaiveTime::from_hms(hour, 0, 0), Forecast { temperature: Some(temperature), ..Forecast::default() });
+        let mixer = Mixer::new(vec![at(12, 1.0), at(9, 2.0), at(12, 3.0), at(9, 4.0)]);
+        let temperatures: Vec<(u32, Option<f32>)> = mixer.data.iter().map(|(time, forecast)| (time.hour(), forecast.temperature)).collect();
+        assert_eq!(temperatures, [(9, Some(4.0)), (12, Some(3.0))]);
+        assert_eq!(mixer.lerp(NaiveTime::from_hms(10, 30, 0)).and_then(|forecast| forecast.temperature), Some(3.5));
+    }
+
+    fn visible(visibility: f32) -> Report {
+        report(vec![(NaiveTime::from_hms(9, 0, 0), Forecast { visibility: Some(visibility), ..Forecast::default() })])
+    }
+
+    #[test]
+    fn visibility_is_labelled_with_its_unit() {
+        assert_eq!(format_visibility(6.2137, true), "6.2mi");
+        assert_eq!(format_visibility(10000.0, false), "10000m");
+    }
+
+    #[test]
+    fn csv_visibility_is_in_miles_with_freedom_units() {
+        let args = Args::parse_from(["weather", "london", "--csv", "--freedom-units"]);
+        let csv = to_csv(&args, visible(units::distance(8046.72, true)));
+        let header: Vec<&str> = csv.lines().next().unwrap().split(',').collect();
+        let row: Vec<&str> = csv.lines().nth(1).unwrap().split(',').collect();
+        let column = header.iter().position(|name| *name == "visibility").unwrap();
+        let miles: f32 = row[column].parse().unwrap();
+        assert!((miles - 5.0).abs() < 0.001, "{} isn't 5 miles", miles);
+    }
+
+    #[test]
+    fn json_visibility_is_in_miles_with_freedom_units() {
+        let args = Args::parse_from(["weather", "london", "--json", "--json-units", "--freedom-units"]);
+        let json: serde_json::Value = serde_json::from_str(&to_json(&args, &data_output(&args, visible(units::distance(16093.44, true)))).unwrap()).unwrap();
+        let visibility = &json["data"][0]["times"][0]["forecast"]["visibility"];
+        assert_eq!(json["meta"]["visibility_unit"], "miles");
+        assert_eq!(visibility["unit"], "miles");
+        assert!((visibility["value"].as_f64().unwrap() - 10.0).abs() < 0.001, "{} isn't 10 miles", visibility["value"]);
+    }
+
+    #[test]
+    fn aggregate_shares_the_summary_wet_hours_and_takes_the_wettest_status() {
+        let at = |hour: u32, status: WeatherStatus, precipitation: f32| {
+            (NaiveTime::from_hms(hour, 0, 0), Forecast { status, precipitation: Some(precipitation), ..Forecast::default() })
+        };
+        let forecasts = vec![
+            at(0, WeatherStatus::ClearNight, 5.0), at(3, WeatherStatus::ClearNight, 5.0), at(6, WeatherStatus::HeavyRain, 84.0),
+            at(9, WeatherStatus::HeavyRain, 84.0), at(12, WeatherStatus::ClearNight, 5.0)
+        ];
+        let mixer = Mixer::new(forecasts.clone());
+        let mut day = report(forecasts).data.remove(0);
+        day.wet_hours = wet_hours(&mixer);
+
+        let aggregate = aggregate_day(&day).unwrap();
+        assert_eq!(aggregate.wet_hours, 6);
+        assert_eq!(aggregate.status, WeatherStatus::HeavyRain);
+    }
+
+    #[test]
+    fn csv_numbers_have_no_conversion_noise() {
+        let args = Args::parse_from(["weather", "london", "--csv", "--decimal-comma"]);
+        let wind = Forecast { wind_speed: Some(units::speed(3.0, false)), wind_gust: Some(units::speed(6.0, false)), ..Forecast::default() };
+        let csv = to_csv(&args, report(vec![(NaiveTime::from_hms(9, 0, 0), wind)]));
+        let header: Vec<&str> = csv.lines().next().unwrap().split(';').collect();
+        let row: Vec<&str> = csv.lines().nth(1).unwrap().split(';').collect();
+        let field = |name: &str| row[header.iter().position(|column| *column == name).unwrap()];
+        assert_eq!((field("wind_speed"), field("wind_gust")), ("10,8", "21,6"));
+        assert_eq!((csv_number(-0.0001), csv_number(29.910002), csv_number(5.0)), ("0".to_string(), "29.91".to_string(), "5".to_string()));
+    }
+
+    #[test]
+    fn weekdays_are_days_from_today() {
+        let wednesday = NaiveDate::from_ymd(2026, 1, 14);
+        let offset = |day: &str| day.parse::<Day>().unwrap().offset(wednesday);
+        assert_eq!((offset("wed"), offset("Thursday"), offset("tue"), offset("2")), (0, 1, 6, 2));
+        assert!("someday".parse::<Day>().is_err());
+    }
+
+    #[test]
+    fn time_range_lists_are_sorted_hours() {
+        assert_eq!("18,9,07,9".parse::<TimeRange>().unwrap().hours(), [7, 9, 18]);
+        assert_eq!("0".parse::<TimeRange>().unwrap().hours(), [0]);
+        assert!("9,24".parse::<TimeRange>().is_err());
+        assert!("9,,12".parse::<TimeRange>().is_err());
+    }
+
+    #[test]
+    fn summary_status_describes_the_dry_and_wet_times() {
+        let at = |hour: u32, status: WeatherStatus| TimeWrapper::new(NaiveTime::from_hms(hour, 0, 0), Forecast { status, ..Forecast::default() });
+        let mut now = at(10, WeatherStatus::HeavyRain);
+        now.now = true;
+        let times = vec![at(9, WeatherStatus::Cloudy), now, at(12, WeatherStatus::Cloudy), at(15, WeatherStatus::LightShowerDay), at(18, WeatherStatus::Cloudy)];
+        assert_eq!(day_summary_status(&times), "mostly cloudy with afternoon showers");
+        assert_eq!(day_summary_status(&[at(9, WeatherStatus::SunnyDay), at(12, WeatherStatus::SunnyDay)]), "sunny");
+        assert_eq!(day_summary_status(&[at(6, WeatherStatus::LightRain), at(21, WeatherStatus::HeavyRain)]), "rain throughout");
+        assert_eq!(day_summary_status(&[]), "");
+    }
+
+    #[test]
+    fn csv_delimiters_are_single_ascii_characters() {
+        assert_eq!(parse_delimiter("tab").unwrap(), '\t');
+        assert_eq!(parse_delimiter("\\t").unwrap(), '\t');
+        assert_eq!(parse_delimiter("|").unwrap(), '|');
+        for delimiter in ["", "||", "\"", "\n", "é"] {
+            assert!(parse_delimiter(delimiter).is_err(), "{:?}", delimiter);
+        }
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_when_needed() {
+        let args = Args::parse_from(["weather", "london", "--csv", "--csv-delimiter", "|"]);
+        let mut report = report(vec![(NaiveTime::from_hms(9, 0, 0), Forecast::default())]);
+        report.location.name = "Say \"hi\" | bye".to_string();
+        report.location.area = Some("Greater London, England".to_string());
+        let csv = to_csv(&args, report);
+        assert!(csv.lines().nth(1).unwrap().starts_with("\"Say \"\"hi\"\" | bye\"|Greater London, England|"), "{}", csv);
+    }
+
+    #[test]
+    fn wind_arrows_point_where_the_wind_blows() {
+        let arrows: Vec<Option<char>> = ["N", "E", "SW", "NNE", "NNW", "calm"].iter().map(|direction| wind_arrow(direction)).collect();
+        assert_eq!(arrows, [Some('↓'), Some('←'), Some('↗'), Some('↙'), Some('↓'), None]);
+    }
+
+    #[test]
+    fn pressure_trend_compares_the_first_and_last_pressures() {
+        let trend = |pressures: &[Option<f32>]| {
+            let times: Vec<TimeWrapper> = pressures.iter().enumerate()
+                .map(|(hour, pressure)| TimeWrapper::new(NaiveTime::from_hms(hour as u32, 0, 0), Forecast { pressure: *pressure, ..Forecast::default() }))
+                .collect();
+            pressure_trend(&times)
+        };
+        assert_eq!(trend(&[Some(1010.0), Some(1000.0), Some(1012.0)]), Some(PressureTrend::Rising));
+        assert_eq!(trend(&[Some(1012.0), None, Some(1010.5)]), Some(PressureTrend::Falling));
+        assert_eq!(trend(&[Some(1010.0), Some(1011.0)]), Some(PressureTrend::Steady));
+        assert_eq!(trend(&[None, Some(1010.0), None]), None);
     }
 }
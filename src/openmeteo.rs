@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use reqwest::header::HeaderMap;
+use chrono::{DateTime, Utc, NaiveDateTime};
+use crate::raw::{self, FetchOptions, Forecast, ForecastDay, TimeZone};
+use crate::status::WeatherStatus;
+use crate::units;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.open-meteo.com";
+
+const HOURLY_FIELDS: &str = "temperature_2m,apparent_temperature,precipitation_probability,weather_code,is_day,wind_speed_10m,wind_direction_10m,wind_gusts_10m,visibility,relative_humidity_2m,uv_index,pressure_msl,snowfall";
+
+#[derive(Deserialize, Debug)]
+struct Response {
+    timezone_abbreviation: String,
+    utc_offset_seconds: i32,
+    hourly: Hourly
+}
+
+/// The hourly forecasts, in Celsius, metres per second, metres, hectopascals and centimetres.
+#[derive(Deserialize, Debug)]
+struct Hourly {
+    time: Vec<String>,
+    temperature_2m: Vec<Option<f32>>,
+    apparent_temperature: Vec<Option<f32>>,
+    precipitation_probability: Vec<Option<f32>>,
+    weather_code: Vec<Option<u8>>,
+    is_day: Vec<Option<u8>>,
+    wind_speed_10m: Vec<Option<f32>>,
+    wind_direction_10m: Vec<Option<f32>>,
+    wind_gusts_10m: Vec<Option<f32>>,
+    visibility: Vec<Option<f32>>,
+    relative_humidity_2m: Vec<Option<f32>>,
+    uv_index: Vec<Option<f32>>,
+    pressure_msl: Vec<Option<f32>>,
+    snowfall: Vec<Option<f32>>
+}
+
+fn compass(degrees: f32) -> String {
+    const POINTS: [&str; 16] = ["N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW"];
+    let index = (degrees.rem_euclid(360.0) / 22.5).round() as usize % 16;
+    POINTS[index].to_string()
+}
+
+/// Gets a forecast for the given coordinates from Open-Meteo, in the same form as a Met Office forecast.
+pub fn get_forecast(latitude: f32, longitude: f32, freedom_units: bool, options: &FetchOptions) -> Result<(DateTime<Utc>, Vec<ForecastDay>)> {
+    let url = format!(
+        "{}/v1/forecast?latitude={}&longitude={}&hourly={}&wind_speed_unit=ms&timezone=auto&forecast_days=7",
+        options.open_meteo_url, latitude, longitude, HOURLY_FIELDS
+    );
+    let key = raw::cache_key(format!("open-meteo-{:.3},{:.3}", latitude, longitude), &options.open_meteo_url, DEFAULT_BASE_URL);
+    let entry = raw::fetch_from("Open-Meteo", &key, url, HeaderMap::new(), options)?;
+    let response: Response = serde_json::from_str(&entry.body).context("malformed Open-Meteo forecast")?;
+    let timezone = TimeZone::new(&response.timezone_abbreviation, response.utc_offset_seconds);
+    Ok((entry.fetched_at, parse_forecast(response.hourly, timezone, freedom_units)?))
+}
+
+/// Open-Meteo only gives the location's current offset from UTC, so it is used for every day.
+fn parse_forecast(hourly: Hourly, timezone: TimeZone, freedom_units: bool) -> Result<Vec<ForecastDay>> {
+    let value = |values: &[Option<f32>], i: usize| values.get(i).copied().flatten();
+
+    let mut days = BTreeMap::new();
+    for (i, time) in hourly.time.iter().enumerate() {
+        let time = NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M").context("malformed time in Open-Meteo forecast")?;
+        let Some(code) = hourly.weather_code.get(i).copied().flatten() else { continue };
+        let is_day = hourly.is_day.get(i).copied().flatten().unwrap_or(1) == 1;
+
+        let status = WeatherStatus::from_wmo(code, is_day);
+        let precipitation = value(&hourly.precipitation_probability, i);
+        let temperature = value(&hourly.temperature_2m, i);
+        let forecast = Forecast {
+            snow_probability: precipitation.map(|precipitation| raw::snow_probability(&status, precipitation, temperature)),
+            status,
+            precipitation,
+            temperature: temperature.map(|t| units::temperature(t, freedom_units)),
+            feels_like: value(&hourly.apparent_temperature, i).map(|t| units::temperature(t, freedom_units)),
+            wind_speed: value(&hourly.wind_speed_10m, i).map(|s| units::speed(s, freedom_units)),
+            wind_direction: value(&hourly.wind_direction_10m, i).map(compass),
+            wind_gust: value(&hourly.wind_gusts_10m, i).map(|s| units::speed(s, freedom_units)),
+            visibility: value(&hourly.visibility, i).map(|d| units::distance(d, freedom_units)),
+            humidity: value(&hourly.relative_humidity_2m, i),
+            uv_index: value(&hourly.uv_index, i),
+            pressure: value(&hourly.pressure_msl, i),
+            snow_amount: value(&hourly.snowfall, i).filter(|d| *d > 0.0).map(|d| units::depth(d, freedom_units))
+        };
+
+        days.entry(time.date()).or_insert_with(Vec::new).push((time.time(), forecast));
+    }
+
+    Ok(days.into_iter()
+        .map(|(date, times)| ForecastDay { date, times, tides: Vec::new(), sea_temperature: None, timezone: timezone.clone() })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_visibility_to_miles() {
+        let hourly: Hourly = serde_json::from_value(serde_json::json!({
+            "time": ["2026-01-14T09:00"], "temperature_2m": [3.0], "apparent_temperature": [1.0],
+            "precipitation_probability": [10.0], "weather_code": [3], "is_day": [1], "wind_speed_10m": [5.0],
+            "wind_direction_10m": [270.0], "wind_gusts_10m": [10.0], "visibility": [8046.72],
+            "relative_humidity_2m": [80.0], "uv_index": [1.0], "pressure_msl": [1013.0], "snowfall": [0.0]
+        })).unwrap();
+        let days = parse_forecast(hourly, TimeZone::new("GMT", 0), true).unwrap();
+        let visibility = days[0].times[0].1.visibility.unwrap();
+        assert!((visibility - 5.0).abs() < 0.001);
+    }
+}
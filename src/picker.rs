@@ -0,0 +1,135 @@
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration;
+use anyhow::{Result, anyhow};
+use console::{Key, Term};
+use crate::cache;
+use crate::raw::{self, FetchOptions, Location, LocationFilter};
+
+/// How long to wait after the last keypress before searching.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+const SHOWN: usize = 8;
+
+#[derive(Default)]
+struct State {
+    query: String,
+    results: Vec<Location>,
+    /// The query and page the results came from, which is only cached once a location is picked from it.
+    page: Option<(String, cache::Entry)>,
+    selected: usize,
+    status: String,
+    drawn: usize
+}
+
+fn render(term: &Term, state: &mut State, ascii: bool) {
+    let mut lines = vec![format!("Search for a location: {}", state.query)];
+    if state.results.is_empty() {
+        lines.push(state.status.clone());
+    } else {
+        let marker = if ascii { ">" } else { "❯" };
+        for (i, loc) in state.results.iter().enumerate().take(SHOWN) {
+            let prefix = if i == state.selected { marker } else { " " };
+            lines.push(format!("{} {} ({})", prefix, loc.name, loc.area.as_deref().unwrap_or("N/A")));
+        }
+    }
+
+    // Lines end with \r\n since the terminal is in raw mode while waiting for a key.
+    let _ = term.clear_last_lines(state.drawn);
+    let _ = term.write_str(&lines.iter().map(|line| format!("\r{}\r\n", line)).collect::<String>());
+    state.drawn = lines.len();
+}
+
+/// Searches for each query as it is typed, once typing pauses, and shows the results.
+fn search_worker(queries: mpsc::Receiver<String>, state: Arc<Mutex<State>>, filters: Vec<LocationFilter>, options: FetchOptions, ascii: bool) {
+    let term = Term::stderr();
+    while let Ok(mut query) = queries.recv() {
+        loop {
+            match queries.recv_timeout(DEBOUNCE) {
+                Ok(newer) => query = newer,
+                Err(mpsc::RecvTimeoutError::Timeout) => break,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return
+            }
+        }
+
+        let results = if query.trim().chars().count() < 2 {
+            Ok((Vec::new(), None))
+        } else {
+            raw::search_results_uncached(&query, &filters, &options).map(|(results, page)| (results, Some((query.clone(), page))))
+        };
+
+        let Ok(mut state) = state.lock() else { return };
+        if state.query != query {
+            continue
+        }
+
+        match results {
+            Ok((results, page)) => {
+                state.results = results.into_iter().filter(|loc| loc.geohash.is_some()).collect();
+                state.page = page;
+                state.status = if query.trim().chars().count() < 2 { String::new() } else { "No matching locations".to_string() };
+            },
+            Err(err) => {
+                state.results.clear();
+                state.page = None;
+                state.status = format!("Search failed: {:#}", err);
+            }
+        }
+        state.selected = 0;
+        render(&term, &mut state, ascii);
+    }
+}
+
+/// Lets the user pick a location while typing its name, with the matching locations updating as they type.
+/// Returns `None` if they give up with escape.
+pub fn pick_location(filters: &[LocationFilter], ascii: bool, options: &FetchOptions) -> Result<Option<Location>> {
+    let term = Term::stderr();
+    if !term.is_term() {
+        return Err(anyhow!("Interactive search needs a terminal."))
+    }
+
+    let state = Arc::new(Mutex::new(State::default()));
+    let (queries, received) = mpsc::channel();
+    let worker = {
+        let (state, filters, options) = (state.clone(), filters.to_vec(), options.clone());
+        std::thread::spawn(move || search_worker(received, state, filters, options, ascii))
+    };
+
+    if let Ok(mut state) = state.lock() {
+        render(&term, &mut state, ascii);
+    }
+
+    let picked = loop {
+        let key = term.read_key()?;
+        let Ok(mut state) = state.lock() else { break None };
+        match key {
+            Key::Escape | Key::Char('\u{3}') => break None,
+            Key::Enter => if let Some(loc) = state.results.get(state.selected) {
+                if let Some((query, page)) = &state.page {
+                    // The picker's own lines are still shown, so a failure to cache is ignored.
+                    let _ = raw::store_search(query, filters, page, options);
+                }
+                break Some(loc.clone().with_coordinates())
+            },
+            Key::ArrowUp => state.selected = state.selected.saturating_sub(1),
+            Key::ArrowDown => state.selected = (state.selected + 1).min(state.results.len().min(SHOWN).saturating_sub(1)),
+            Key::Backspace => {
+                state.query.pop();
+                state.status = "Searching...".to_string();
+                let _ = queries.send(state.query.clone());
+            },
+            Key::Char(c) if !c.is_control() => {
+                state.query.push(c);
+                state.status = "Searching...".to_string();
+                let _ = queries.send(state.query.clone());
+            },
+            _ => ()
+        }
+        render(&term, &mut state, ascii);
+    };
+
+    drop(queries);
+    let _ = worker.join();
+    if let Ok(state) = state.lock() {
+        term.clear_last_lines(state.drawn)?;
+    }
+    Ok(picked)
+}
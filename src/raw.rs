@@ -1,43 +1,296 @@
 use std::process::Command;
+use std::path::PathBuf;
 use anyhow::{Context, Result, anyhow};
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 use dialoguer::{Select, theme};
 use console::Term;
-use chrono::{NaiveDate, NaiveTime};
+use std::fmt;
+use chrono::{NaiveDate, NaiveTime, DateTime, Utc, FixedOffset, Duration, Datelike, Weekday};
+use reqwest::{StatusCode, header::{self, HeaderMap, HeaderValue}};
+use crate::cache::{self, CachePolicy};
+use crate::status::WeatherStatus;
+use crate::config::Selectors;
+use crate::what3words;
+use crate::units;
 
 fn get_current_location() -> Result<(f32, f32)> {
     let command = Command::new("powershell")
-        .args(&["-encodedCommand", "QQBkAGQALQBUAHkAcABlACAALQBBAHMAcwBlAG0AYgBsAHkATgBhAG0AZQAgAFMAeQBzAHQAZQBtAC4ARABlAHYAaQBjAGUACgAkAEcAZQBvAFcAYQB0AGMAaABlAHIAIAA9ACAATgBlAHcALQBPAGIAagBlAGMAdAAgAFMAeQBzAHQAZQBtAC4ARABlAHYAaQBjAGUALgBMAG8AYwBhAHQAaQBvAG4ALgBHAGUAbwBDAG8AbwByAGQAaQBuAGEAdABlAFcAYQB0AGMAaABlAHIACgAkAEcAZQBvAFcAYQB0AGMAaABlAHIALgBTAHQAYQByAHQAKAApAAoACgB3AGgAaQBsAGUAIAAoACgAJABHAGUAbwBXAGEAdABjAGgAZQByAC4AUwB0AGEAdAB1AHMAIAAtAG4AZQAgACcAUgBlAGEAZAB5ACcAKQAgAC0AYQBuAGQAIAAoACQARwBlAG8AVwBhAHQAYwBoAGUAcgAuAFAAZQByAG0AaQBzAHMAaQBvAG4AIAAtAG4AZQAgACcARABlAG4AaQBlAGQAJwApACkAIAB7AAoAIAAgACAAIABTAHQAYQByAHQALQBTAGwAZQBlAHAAIAAtAE0AaQBsAGwAaQBzAGUAYwBvAG4AZABzACAAMQAwADAACgB9ACAAIAAKAAoAaQBmACAAKAAkAEcAZQBvAFcAYQB0AGMAaABlAHIALgBQAGUAcgBtAGkAcwBzAGkAbwBuACAALQBlAHEAIAAnAEQAZQBuAGkAZQBkACcAKQB7AAoAIAAgACAAIABXAHIAaQB0AGUALQBPAHUAdABwAHUAdAAgACcATgBPACcACgB9ACAAZQBsAHMAZQAgAHsACgAgACAAIAAgAFcAcgBpAHQAZQAtAE8AdQB0AHAAdQB0ACAAJwBPAEsAJwA7ACAAVwByAGkAdABlAC0ATwB1AHQAcAB1AHQAIAAkAEcAZQBvAFcAYQB0AGMAaABlAHIALgBQAG8AcwBpAHQAaQBvAG4ALgBMAG8AYwBhAHQAaQBvAG4ALgBMAGEAdABpAHQAdQBkAGUAOwAgAFcAcgBpAHQAZQAtAE8AdQB0AHAAdQB0ACAAJABHAGUAbwBXAGEAdABjAGgAZQByAC4AUABvAHMAaQB0AGkAbwBuAC4ATABvAGMAYQB0AGkAbwBuAC4ATABvAG4AZwBpAHQAdQBkAGUACgB9AA=="])
+        .args(["-encodedCommand", "QQBkAGQALQBUAHkAcABlACAALQBBAHMAcwBlAG0AYgBsAHkATgBhAG0AZQAgAFMAeQBzAHQAZQBtAC4ARABlAHYAaQBjAGUACgAkAEcAZQBvAFcAYQB0AGMAaABlAHIAIAA9ACAATgBlAHcALQBPAGIAagBlAGMAdAAgAFMAeQBzAHQAZQBtAC4ARABlAHYAaQBjAGUALgBMAG8AYwBhAHQAaQBvAG4ALgBHAGUAbwBDAG8AbwByAGQAaQBuAGEAdABlAFcAYQB0AGMAaABlAHIACgAkAEcAZQBvAFcAYQB0AGMAaABlAHIALgBTAHQAYQByAHQAKAApAAoACgB3AGgAaQBsAGUAIAAoACgAJABHAGUAbwBXAGEAdABjAGgAZQByAC4AUwB0AGEAdAB1AHMAIAAtAG4AZQAgACcAUgBlAGEAZAB5ACcAKQAgAC0AYQBuAGQAIAAoACQARwBlAG8AVwBhAHQAYwBoAGUAcgAuAFAAZQByAG0AaQBzAHMAaQBvAG4AIAAtAG4AZQAgACcARABlAG4AaQBlAGQAJwApACkAIAB7AAoAIAAgACAAIABTAHQAYQByAHQALQBTAGwAZQBlAHAAIAAtAE0AaQBsAGwAaQBzAGUAYwBvAG4AZABzACAAMQAwADAACgB9ACAAIAAKAAoAaQBmACAAKAAkAEcAZQBvAFcAYQB0AGMAaABlAHIALgBQAGUAcgBtAGkAcwBzAGkAbwBuACAALQBlAHEAIAAnAEQAZQBuAGkAZQBkACcAKQB7AAoAIAAgACAAIABXAHIAaQB0AGUALQBPAHUAdABwAHUAdAAgACcATgBPACcACgB9ACAAZQBsAHMAZQAgAHsACgAgACAAIAAgAFcAcgBpAHQAZQAtAE8AdQB0AHAAdQB0ACAAJwBPAEsAJwA7ACAAVwByAGkAdABlAC0ATwB1AHQAcAB1AHQAIAAkAEcAZQBvAFcAYQB0AGMAaABlAHIALgBQAG8AcwBpAHQAaQBvAG4ALgBMAG8AYwBhAHQAaQBvAG4ALgBMAGEAdABpAHQAdQBkAGUAOwAgAFcAcgBpAHQAZQAtAE8AdQB0AHAAdQB0ACAAJABHAGUAbwBXAGEAdABjAGgAZQByAC4AUABvAHMAaQB0AGkAbwBuAC4ATABvAGMAYQB0AGkAbwBuAC4ATABvAG4AZwBpAHQAdQBkAGUACgB9AA=="])
         .output()?;
-    let output = String::from_utf8(command.stdout)?;
+    // PowerShell may start its output with a byte order mark, and write in the console's code
+    // page rather than UTF8 on some locales, but the parts we need are always ASCII.
+    let output = String::from_utf8_lossy(&command.stdout);
+    let output = output.trim_start_matches('\u{FEFF}');
 
-    match &output[..2] {
-        "OK" => {
-            let mut iter = output.lines().skip(1).map(str::parse::<f32>);
+    match output.lines().next().map(str::trim) {
+        Some("OK") => {
+            let mut iter = output.lines().skip(1).map(|line| line.trim().parse::<f32>());
             let latitude = iter.next().context("malformed powershell output")??;
             let longitude = iter.next().context("malformed powershell output")??;
+            if !is_valid_fix(latitude, longitude) {
+                return Err(anyhow!("couldn't get a valid fix from location services"))
+            }
             Ok((latitude, longitude))
         },
-        "NO" => Err(anyhow!("permission denied or location unavailable")),
-        _ => Err(anyhow!("malformed powershell output"))
+        Some("NO") => Err(anyhow!("permission denied or location unavailable")),
+        None => Err(anyhow!("no output from powershell")),
+        Some(_) => Err(anyhow!("malformed powershell output"))
     }
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+/// Rejects coordinates that are out of range, or exactly 0,0, which location services
+/// sometimes report when they don't have a real fix.
+fn is_valid_fix(latitude: f32, longitude: f32) -> bool {
+    let in_range = (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude);
+    in_range && !(latitude == 0.0 && longitude == 0.0)
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
 pub struct Location {
     pub name: String,
     pub area: Option<String>,
-    pub geohash: Option<String>
+    pub geohash: Option<String>,
+    #[serde(default)]
+    pub latitude: Option<f32>,
+    #[serde(default)]
+    pub longitude: Option<f32>
+}
+
+impl Location {
+    pub(crate) fn with_coordinates(mut self) -> Location {
+        if let (None, None, Some((latitude, longitude))) = (self.latitude, self.longitude, self.geohash.as_deref().and_then(decode_geohash)) {
+            self.latitude = Some(latitude);
+            self.longitude = Some(longitude);
+        }
+        self
+    }
+}
+
+pub fn decode_geohash(hash: &str) -> Option<(f32, f32)> {
+    const ALPHABET: &str = "0123456789bcdefghjkmnpqrstuvwxyz";
+
+    let mut latitude = (-90.0f64, 90.0f64);
+    let mut longitude = (-180.0f64, 180.0f64);
+    let mut is_longitude = true;
+    for c in hash.chars() {
+        let value = ALPHABET.find(c.to_ascii_lowercase())?;
+        for bit in (0..5).rev() {
+            let range = if is_longitude { &mut longitude } else { &mut latitude };
+            let mid = (range.0 + range.1) / 2.0;
+            if value & (1 << bit) != 0 {
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            is_longitude = !is_longitude;
+        }
+    }
+
+    if hash.is_empty() {
+        None
+    } else {
+        Some((((latitude.0 + latitude.1) / 2.0) as f32, ((longitude.0 + longitude.1) / 2.0) as f32))
+    }
 }
 
-enum LocationFilter {
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+pub enum LocationFilter {
     Domestic,
     Beaches,
     NoCountries,
     NoUKRegions
 }
 
-fn raw_search_location(term: &str, filters: &[LocationFilter]) -> Result<Vec<Location>> {
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    pub filters: Vec<LocationFilter>,
+    pub region: Option<String>,
+    pub pick_best: bool,
+    /// The most locations offered to pick from, or zero for all of them.
+    pub max_results: usize,
+    /// Note each step of finding the location on stderr.
+    pub explain: bool
+}
+
+pub const DEFAULT_BASE_URL: &str = "https://www.metoffice.gov.uk";
+
+/// Names the cache entry for a page from `base_url`. Pages from anywhere but `default_url`, such
+/// as a fixture server, are kept apart so that they never replace the real ones.
+pub(crate) fn cache_key(key: String, base_url: &str, default_url: &str) -> String {
+    if base_url == default_url {
+        return key
+    }
+    let host = base_url.split_once("://").map_or(base_url, |(_, rest)| rest);
+    format!("{}@{}", key, host)
+}
+
+fn forecast_key(geohash: &str, options: &FetchOptions) -> String {
+    cache_key(format!("forecast-{}", geohash), &options.base_url, DEFAULT_BASE_URL)
+}
+
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    pub policy: CachePolicy,
+    /// Used instead of `policy` for location searches, which can be kept for much longer.
+    pub location_policy: CachePolicy,
+    pub base_url: String,
+    pub open_meteo_url: String,
+    pub what3words_url: String,
+    pub what3words_key: Option<String>,
+    pub cache_dir: Option<PathBuf>,
+    pub selectors: Selectors,
+    pub strict: bool,
+    /// Whether fetched pages are stored in the cache, as well as read from it.
+    pub store: bool,
+    /// Warn about cache entries that can't be read and are fetched again.
+    pub explain: bool
+}
+
+/// Treats a cache entry that can't be read, such as one left by an older version, as missing so
+/// that it is fetched again. Offline, there is nothing to fetch it from, so that is an error.
+fn readable(loaded: Result<Option<cache::Entry>>, key: &str, options: &FetchOptions) -> Result<Option<cache::Entry>> {
+    match loaded {
+        Err(err) if !matches!(options.policy, CachePolicy::Offline) => {
+            if options.explain {
+                eprintln!("Warning: ignoring cached {}: {:#}", key, err);
+            }
+            Ok(None)
+        },
+        loaded => loaded
+    }
+}
+
+pub(crate) fn load_cached(key: &str, options: &FetchOptions) -> Result<Option<cache::Entry>> {
+    readable(cache::load(options.cache_dir.as_deref(), key), key, options)
+}
+
+fn cached(key: &str, options: &FetchOptions) -> Result<Option<cache::Entry>> {
+    match options.policy {
+        CachePolicy::Offline => return load_cached(key, options)?.context("no cached data for this location").map(Some),
+        CachePolicy::MaxAge(max_age) => if let Some(entry) = load_cached(key, options)? {
+            if Utc::now() - entry.fetched_at < max_age {
+                return Ok(Some(entry))
+            }
+        },
+        CachePolicy::Refresh => ()
+    }
+
+    Ok(None)
+}
+
+/// Headers for a conditional request, so an unchanged page isn't downloaded again.
+fn conditional_headers(entry: Option<&cache::Entry>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let Some(entry) = entry else { return headers };
+
+    if let Some(etag) = entry.etag.as_deref().and_then(|etag| HeaderValue::from_str(etag).ok()) {
+        headers.insert(header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = entry.last_modified.as_deref().and_then(|date| HeaderValue::from_str(date).ok()) {
+        headers.insert(header::IF_MODIFIED_SINCE, last_modified);
+    }
+    headers
+}
+
+fn header_string(headers: &HeaderMap, name: header::HeaderName) -> Option<String> {
+    headers.get(name)?.to_str().ok().map(str::to_string)
+}
+
+/// The page has been downloaded even if it can't be cached, so that is only warned about.
+fn keep(saved: Result<()>, key: &str, entry: cache::Entry) -> cache::Entry {
+    if let Err(err) = saved {
+        eprintln!("Warning: can't cache {}: {:#}", key, err);
+    }
+    entry
+}
+
+/// Where a fetch starts from: a cached entry that can be used as it is, or the previous entry, if
+/// any, which is used to ask for the page only if it has changed.
+enum Lookup {
+    Fresh(cache::Entry),
+    Stale(Option<cache::Entry>)
+}
+
+fn lookup(key: &str, options: &FetchOptions) -> Result<Lookup> {
+    match cached(key, options)? {
+        Some(entry) => Ok(Lookup::Fresh(entry)),
+        None => Ok(Lookup::Stale(load_cached(key, options)?))
+    }
+}
+
+/// The previous entry, refreshed, if the server reports that the page hasn't changed since.
+fn unchanged(status: StatusCode, previous: Option<cache::Entry>, key: &str, options: &FetchOptions) -> Option<cache::Entry> {
+    let (StatusCode::NOT_MODIFIED, Some(previous)) = (status, previous) else { return None };
+    let entry = cache::Entry { fetched_at: Utc::now(), ..previous };
+    Some(if options.store { keep(cache::refresh(options.cache_dir.as_deref(), key, &entry), key, entry) } else { entry })
+}
+
+fn entry_from_response(key: &str, headers: &HeaderMap, body: String, options: &FetchOptions) -> cache::Entry {
+    let entry = cache::Entry { fetched_at: Utc::now(), body, etag: header_string(headers, header::ETAG), last_modified: header_string(headers, header::LAST_MODIFIED) };
+    if options.store { keep(cache::store(options.cache_dir.as_deref(), key, &entry), key, entry) } else { entry }
+}
+
+fn fetch(key: &str, url: String, options: &FetchOptions) -> Result<cache::Entry> {
+    fetch_from("Met Office", key, url, HeaderMap::new(), options)
+}
+
+/// Fetches a page, sending `headers` along with any needed for a conditional request.
+pub(crate) fn fetch_from(provider: &str, key: &str, url: String, mut headers: HeaderMap, options: &FetchOptions) -> Result<cache::Entry> {
+    let previous = match lookup(key, options)? {
+        Lookup::Fresh(entry) => return Ok(entry),
+        Lookup::Stale(previous) => previous
+    };
+
+    headers.extend(conditional_headers(previous.as_ref()));
+    let response = reqwest::blocking::Client::new().get(url).headers(headers).send()?;
+    let (status, response_headers) = (response.status(), response.headers().clone());
+    if let Some(entry) = unchanged(status, previous, key, options) {
+        return Ok(entry)
+    }
+
+    let body = response.error_for_status()
+        .with_context(|| format!("{} returned {}", provider, describe_status(status)))?
+        .text()?;
+    Ok(entry_from_response(key, &response_headers, body, options))
+}
+
+async fn fetch_async(client: &reqwest::Client, key: &str, url: String, options: &FetchOptions) -> Result<cache::Entry> {
+    fetch_async_from(client, "Met Office", key, url, HeaderMap::new(), options).await
+}
+
+pub(crate) async fn fetch_async_from(client: &reqwest::Client, provider: &str, key: &str, url: String, mut headers: HeaderMap, options: &FetchOptions) -> Result<cache::Entry> {
+    let previous = match lookup(key, options)? {
+        Lookup::Fresh(entry) => return Ok(entry),
+        Lookup::Stale(previous) => previous
+    };
+
+    headers.extend(conditional_headers(previous.as_ref()));
+    let response = client.get(url).headers(headers).send().await?;
+    let (status, response_headers) = (response.status(), response.headers().clone());
+    if let Some(entry) = unchanged(status, previous, key, options) {
+        return Ok(entry)
+    }
+
+    let body = response.error_for_status()
+        .with_context(|| format!("{} returned {}", provider, describe_status(status)))?
+        .text().await?;
+    Ok(entry_from_response(key, &response_headers, body, options))
+}
+
+fn describe_status(status: StatusCode) -> String {
+    let description = match status.as_u16() {
+        404 => "location/forecast page not found",
+        429 => "too many requests, try again later",
+        503 => "service temporarily unavailable",
+        500..=599 => "server error",
+        _ => status.canonical_reason().unwrap_or("unexpected response")
+    };
+    format!("{} - {}", status.as_u16(), description)
+}
+
+fn search_request(term: &str, filters: &[LocationFilter], options: &FetchOptions) -> (String, String) {
     let filter = filters.iter().map(|filter| match filter {
         LocationFilter::Domestic => "domestic",
         LocationFilter::Beaches => "beaches",
@@ -45,9 +298,8 @@ fn raw_search_location(term: &str, filters: &[LocationFilter]) -> Result<Vec<Loc
         LocationFilter::NoUKRegions => "no-uk-regions"
     }).collect::<String>();
     let term = urlencoding::encode(term);
-    let url = format!("https://www.metoffice.gov.uk/plain-rest-services/location-search/{}/?filter={}", term, filter);
-
-    Ok(reqwest::blocking::get(url)?.json::<Vec<Location>>()?)
+    let url = format!("{}/plain-rest-services/location-search/{}/?filter={}", options.base_url, term, filter);
+    (cache_key(format!("search-{}-{}", term, filter), &options.base_url, DEFAULT_BASE_URL), url)
 }
 
 #[derive(Debug, Clone)]
@@ -57,23 +309,31 @@ enum FoundLocation {
     NotFound
 }
 
-fn search_location(term: &str, filters: &[LocationFilter]) -> Result<FoundLocation> {
+/// A postcode, or just its outward code, which is its district.
+const POSTCODE_PATTERN: &str = "^([a-zA-Z]{1,2}[0-9][a-zA-Z0-9]?) ?([0-9][a-zA-Z]{0,2})?$";
+
+fn is_postcode(term: &str) -> Result<bool> {
+    let cleaning_regex = regex::Regex::new(r"\s+")?;
+    Ok(regex::Regex::new(POSTCODE_PATTERN)?.is_match(&cleaning_regex.replace_all(term.trim(), " ")))
+}
+
+fn clean_search_term(term: &str) -> Result<String> {
     let cleaning_regex = regex::Regex::new(r"\s+")?;
     let term = cleaning_regex.replace_all(term.trim(), " ").to_ascii_lowercase();
     
-    let postcode_regex = regex::Regex::new("^([a-zA-Z]{1,2}[0-9][a-zA-Z0-9]?) ?([0-9][a-zA-Z]{0,2})?$")?;
-    let cleaned = if let Some(captures) = postcode_regex.captures(&term) {
-        captures.get(1).context("malformed regex result")?.as_str().to_ascii_uppercase()
+    let postcode_regex = regex::Regex::new(POSTCODE_PATTERN)?;
+    if let Some(captures) = postcode_regex.captures(&term) {
+        Ok(captures.get(1).context("malformed regex result")?.as_str().to_ascii_uppercase())
     } else {
-        term
-    };
+        Ok(term)
+    }
+}
 
-    let results = raw_search_location(&cleaned, filters)?;
-    
-    if results.len() == 0 {
-        Ok(FoundLocation::NotFound)
+fn choose_search_result(results: Vec<Location>, cleaned: &str) -> FoundLocation {
+    if results.is_empty() {
+        FoundLocation::NotFound
     } else if results.len() == 1 {
-        Ok(FoundLocation::Found(results[0].clone()))
+        FoundLocation::Found(results[0].clone())
     } else {
         let mut same = results.iter().filter(|loc| {
             let name = loc.name.trim().to_ascii_lowercase();
@@ -85,16 +345,47 @@ fn search_location(term: &str, filters: &[LocationFilter]) -> Result<FoundLocati
 
         if let Some(loc) = loc {
             if amb {
-                Ok(FoundLocation::Ambiguous(results))
+                FoundLocation::Ambiguous(results)
             } else {
-                Ok(FoundLocation::Found(loc.clone()))
+                FoundLocation::Found(loc.clone())
             }
         } else {
-            Ok(FoundLocation::Ambiguous(results))
+            FoundLocation::Ambiguous(results)
         }
     }
 }
 
+/// Every location matching a search term, in the Met Office's order.
+pub fn search_results(term: &str, filters: &[LocationFilter], options: &FetchOptions) -> Result<Vec<Location>> {
+    let cleaned = clean_search_term(term)?;
+    let (key, url) = search_request(&cleaned, filters, options);
+    let entry = fetch(&key, url, &FetchOptions { policy: options.location_policy, ..options.clone() })?;
+    Ok(serde_json::from_str::<Vec<Location>>(&entry.body)?)
+}
+
+/// Like `search_results`, but without storing the results in the cache, for searches that may
+/// be abandoned. The page is returned so that it can be stored with `store_search` if it isn't.
+pub fn search_results_uncached(term: &str, filters: &[LocationFilter], options: &FetchOptions) -> Result<(Vec<Location>, cache::Entry)> {
+    let cleaned = clean_search_term(term)?;
+    let (key, url) = search_request(&cleaned, filters, options);
+    let entry = fetch(&key, url, &FetchOptions { policy: options.location_policy, store: false, ..options.clone() })?;
+    Ok((serde_json::from_str::<Vec<Location>>(&entry.body)?, entry))
+}
+
+pub fn store_search(term: &str, filters: &[LocationFilter], entry: &cache::Entry, options: &FetchOptions) -> Result<()> {
+    let cleaned = clean_search_term(term)?;
+    let (key, _) = search_request(&cleaned, filters, options);
+    cache::store(options.cache_dir.as_deref(), &key, entry)
+}
+
+async fn search_location_async(client: &reqwest::Client, term: &str, filters: &[LocationFilter], options: &FetchOptions) -> Result<FoundLocation> {
+    let cleaned = clean_search_term(term)?;
+    let (key, url) = search_request(&cleaned, filters, options);
+    let entry = fetch_async(client, &key, url, &FetchOptions { policy: options.location_policy, ..options.clone() }).await?;
+    let results = serde_json::from_str::<Vec<Location>>(&entry.body)?;
+    Ok(choose_search_result(results, &cleaned))
+}
+
 #[derive(Deserialize, Debug)]
 struct NearestLocations {
     #[serde(rename = "locationResults")]
@@ -107,10 +398,17 @@ struct NearestLocationEntry {
     distance: f32
 }
 
-fn nearest_location(latitude: f32, longitude: f32) -> Result<FoundLocation> {
-    let url = format!("https://www.metoffice.gov.uk/plain-rest-services/nearest-locations?latitude={}&longitude={}", latitude, longitude);
-    let results = reqwest::blocking::get(url)?.json::<NearestLocations>()?.locations;
-    if results.len() == 0 {
+fn nearest_request(latitude: f32, longitude: f32, options: &FetchOptions) -> Result<(String, String)> {
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(anyhow!("{},{} isn't a valid location - latitudes are between -90 and 90, and longitudes between -180 and 180", latitude, longitude))
+    }
+    let url = format!("{}/plain-rest-services/nearest-locations?latitude={}&longitude={}", options.base_url, latitude, longitude);
+    Ok((cache_key(format!("nearest-{}-{}", latitude, longitude), &options.base_url, DEFAULT_BASE_URL), url))
+}
+
+fn choose_nearest_result(body: &str) -> Result<FoundLocation> {
+    let results = serde_json::from_str::<NearestLocations>(body)?.locations;
+    if results.is_empty() {
         Ok(FoundLocation::NotFound)
     } else if results.len() == 1 {
         Ok(FoundLocation::Found(results[0].result.clone()))
@@ -122,91 +420,499 @@ fn nearest_location(latitude: f32, longitude: f32) -> Result<FoundLocation> {
     }
 }
 
-pub fn get_location(location: Option<String>, non_interactive: bool, ascii: bool, bar: indicatif::ProgressBar) -> Result<Option<Location>> {
-    let possibles = match location {
+fn nearest_location(latitude: f32, longitude: f32, options: &FetchOptions) -> Result<FoundLocation> {
+    let (key, url) = nearest_request(latitude, longitude, options)?;
+    let entry = fetch(&key, url, &FetchOptions { policy: options.location_policy, ..options.clone() })?;
+    choose_nearest_result(&entry.body)
+}
+
+async fn nearest_location_async(client: &reqwest::Client, latitude: f32, longitude: f32, options: &FetchOptions) -> Result<FoundLocation> {
+    let (key, url) = nearest_request(latitude, longitude, options)?;
+    let entry = fetch_async(client, &key, url, &FetchOptions { policy: options.location_policy, ..options.clone() }).await?;
+    choose_nearest_result(&entry.body)
+}
+
+pub fn parse_coordinates(term: &str) -> Option<(f32, f32)> {
+    let coords_regex = regex::Regex::new(r"^\s*(-?[0-9]+(?:\.[0-9]+)?)\s*,\s*(-?[0-9]+(?:\.[0-9]+)?)\s*$").ok()?;
+    let captures = coords_regex.captures(term)?;
+    let latitude = captures.get(1)?.as_str().parse().ok()?;
+    let longitude = captures.get(2)?.as_str().parse().ok()?;
+    Some((latitude, longitude))
+}
+
+/// Picks out the ambiguous candidates whose area matches the region, if there are any.
+fn narrow_by_region(found: FoundLocation, region: Option<&str>) -> FoundLocation {
+    let (FoundLocation::Ambiguous(locs), Some(region)) = (&found, region) else { return found };
+
+    let region = region.trim().to_lowercase();
+    let matching: Vec<Location> = locs.iter()
+        .filter(|loc| loc.area.as_deref().is_some_and(|area| area.to_lowercase().contains(&region)))
+        .cloned()
+        .collect();
+
+    match matching.len() {
+        0 => found,
+        1 => FoundLocation::Found(matching[0].clone()),
+        _ => FoundLocation::Ambiguous(matching)
+    }
+}
+
+/// Picks the first of several ambiguous locations, which is the most relevant one in the
+/// order the Met Office gives them, noting the choice on stderr.
+fn pick_best(locs: &[Location]) -> Option<Location> {
+    let loc = locs.first()?;
+    eprintln!("Picked {} ({}) out of {} matching locations", loc.name, loc.area.as_deref().unwrap_or("N/A"), locs.len());
+    Some(loc.clone().with_coordinates())
+}
+
+pub fn get_location(location: Option<String>, search: &SearchOptions, non_interactive: bool, ascii: bool, options: &FetchOptions, bar: indicatif::ProgressBar) -> Result<Option<Location>> {
+    let explain = |message: String| if search.explain {
+        if bar.is_hidden() {
+            eprintln!("Location: {}", message)
+        } else {
+            bar.println(format!("Location: {}", message))
+        }
+    };
+    let nearest = |latitude: f32, longitude: f32| {
+        explain(format!("finding the nearest named location to {},{}", latitude, longitude));
+        nearest_location(latitude, longitude, options).map(|found| (found, "it is the nearest named location"))
+    };
+
+    let (possibles, reason) = match location {
         None => {
             let (latitude, longitude) = get_current_location()?;
-            nearest_location(latitude, longitude)?
+            explain("no location was given, so using the current location".to_string());
+            nearest(latitude, longitude)?
         },
-        Some(term) => search_location(&term, &[])?
+        Some(term) => if let Some((latitude, longitude)) = parse_coordinates(&term) {
+            explain(format!("\"{}\" is a latitude and longitude", term.trim()));
+            nearest(latitude, longitude)?
+        } else if let Some(words) = what3words::parse_address(&term) {
+            let (latitude, longitude) = what3words::coordinates(&words, options)?;
+            explain(format!("\"{}\" is a what3words address", term.trim()));
+            nearest(latitude, longitude)?
+        } else {
+            let cleaned = clean_search_term(&term)?;
+            if is_postcode(&term)? {
+                explain(format!("\"{}\" is a postcode, so searching for its district \"{}\"", term.trim(), cleaned));
+            } else {
+                explain(format!("searching for \"{}\"", cleaned));
+            }
+
+            let results = search_results(&term, &search.filters, options)?;
+            explain(match results.len() {
+                1 => "1 matching location".to_string(),
+                n => format!("{} matching locations", n)
+            });
+            let reason = if results.len() == 1 { "it is the only match" } else { "its name matches exactly" };
+            (choose_search_result(results, &cleaned), reason)
+        }
     };
 
-    match possibles {
+    let narrowed = narrow_by_region(possibles.clone(), search.region.as_deref());
+    let reason = match (&possibles, &narrowed, &search.region) {
+        (FoundLocation::Ambiguous(_), FoundLocation::Found(_), Some(region)) => format!("it is the only match in {}", region),
+        _ => reason.to_string()
+    };
+    let chosen = |loc: Location, reason: &str| {
+        explain(format!("chose {} ({}) because {}", loc.name, loc.area.as_deref().unwrap_or("N/A"), reason));
+        loc
+    };
+
+    match narrowed {
         FoundLocation::NotFound => Ok(None),
-        FoundLocation::Found(loc) => Ok(Some(loc)),
+        FoundLocation::Found(loc) if loc.geohash.is_none() => {
+            let narrower = narrower_locations(&loc, search, options)?;
+            if narrower.is_empty() {
+                return Ok(Some(chosen(loc.with_coordinates(), &format!("{}, and nothing narrower was found", reason))))
+            }
+            explain(format!("{} is too broad to forecast, so looking within it, and found {} locations", loc.name, narrower.len()));
+
+            if non_interactive {
+                if search.pick_best {
+                    return Ok(pick_best(&narrower).map(|loc| chosen(loc, "it is the most relevant")))
+                }
+
+                let suggestions: Vec<String> = narrower.iter().take(5).map(|l| format!("{} ({})", l.name, l.area.as_deref().unwrap_or("N/A"))).collect();
+                return Err(anyhow!("That location is too broad, please pick a more specific location, such as {}.", suggestions.join(", ")))
+            }
+
+            select_location(narrower, "That location is too broad - please pick one of the following", search.max_results, ascii, bar.clone())
+                .map(|loc| Some(chosen(loc, "you picked it")))
+        },
+        FoundLocation::Found(loc) => Ok(Some(chosen(loc.with_coordinates(), &reason))),
         FoundLocation::Ambiguous(locs) => {
+            explain(format!("no one location stands out of the {} matches", locs.len()));
             if non_interactive {
-                return Ok(None)
+                return Ok(if search.pick_best { pick_best(&locs).map(|loc| chosen(loc, "it is the most relevant")) } else { None })
             }
 
-            bar.finish_and_clear();
+            select_location(locs, "That location is ambiguous - please pick one of the following", search.max_results, ascii, bar.clone())
+                .map(|loc| Some(chosen(loc, "you picked it")))
+        }
+    }
+}
 
-            let items: Vec<String> = locs.iter().map(|l| {
-                format!("{} ({})", l.name, l.area.as_deref().unwrap_or("N/A"))
-            }).collect();
-            let theme: Box<dyn theme::Theme> = if ascii {
-                Box::new(theme::SimpleTheme)
-            } else {
-                Box::new(theme::ColorfulTheme::default())
-            };
-            let selection = Select::with_theme(&*theme)
-                .with_prompt("That location is ambiguous - please pick one of the following")
-                .items(&items)
-                .default(0)
-                .clear(true)
-                .interact_on(&Term::stderr())?;
+/// Finds specific locations within a broad one, such as a county, which has no forecast of its own.
+/// Locations whose area is the broad location are preferred, if there are any.
+fn narrower_locations(broad: &Location, search: &SearchOptions, options: &FetchOptions) -> Result<Vec<Location>> {
+    let mut filters = search.filters.clone();
+    filters.extend([LocationFilter::NoUKRegions, LocationFilter::NoCountries]);
 
-            bar.reset();
-            bar.enable_steady_tick(100);
+    let cleaned = clean_search_term(&broad.name)?;
+    let (key, url) = search_request(&cleaned, &filters, options);
+    let entry = fetch(&key, url, &FetchOptions { policy: options.location_policy, ..options.clone() })?;
+    let results: Vec<Location> = serde_json::from_str::<Vec<Location>>(&entry.body)?
+        .into_iter()
+        .filter(|loc| loc.geohash.is_some())
+        .collect();
 
-            Ok(Some(locs[selection].clone()))
+    let within: Vec<Location> = results.iter()
+        .filter(|loc| loc.area.as_deref().is_some_and(|area| area.to_lowercase().contains(&cleaned)))
+        .cloned()
+        .collect();
+    Ok(if within.is_empty() { results } else { within })
+}
+
+fn select_location(mut locs: Vec<Location>, prompt: &str, max_results: usize, ascii: bool, bar: indicatif::ProgressBar) -> Result<Location> {
+    bar.finish_and_clear();
+
+    let prompt = if max_results > 0 && locs.len() > max_results {
+        let more = locs.len() - max_results;
+        locs.truncate(max_results);
+        format!("{} ({}and {} more, refine your search)", prompt, if ascii { "..." } else { "…" }, more)
+    } else {
+        prompt.to_string()
+    };
+
+    let items: Vec<String> = locs.iter().map(|l| {
+        format!("{} ({})", l.name, l.area.as_deref().unwrap_or("N/A"))
+    }).collect();
+    let theme: Box<dyn theme::Theme> = if ascii {
+        Box::new(theme::SimpleTheme)
+    } else {
+        Box::new(theme::ColorfulTheme::default())
+    };
+    let selection = Select::with_theme(&*theme)
+        .with_prompt(&prompt)
+        .items(&items)
+        .default(0)
+        .clear(true)
+        .interact_on(&Term::stderr())?;
+
+    bar.reset();
+    bar.enable_steady_tick(100);
+
+    Ok(locs[selection].clone().with_coordinates())
+}
+
+/// The URLs that would be fetched to forecast a location, found without using the network.
+/// The forecast URL is only known if the location search result is already cached.
+pub fn dry_run(location: Option<&str>, search: &SearchOptions, options: &FetchOptions) -> Result<Vec<String>> {
+    let mut urls = Vec::new();
+    let (key, url, cleaned) = match location {
+        None => {
+            let (latitude, longitude) = get_current_location()?;
+            let (key, url) = nearest_request(latitude, longitude, options)?;
+            (key, url, None)
+        },
+        Some(term) => if let Some((latitude, longitude)) = parse_coordinates(term) {
+            let (key, url) = nearest_request(latitude, longitude, options)?;
+            (key, url, None)
+        } else if let Some(words) = what3words::parse_address(term) {
+            urls.push(what3words::url(&words, options));
+            let Some((latitude, longitude)) = what3words::cached_coordinates(&words, options)? else { return Ok(urls) };
+            let (key, url) = nearest_request(latitude, longitude, options)?;
+            (key, url, None)
+        } else {
+            let cleaned = clean_search_term(term)?;
+            let (key, url) = search_request(&cleaned, &search.filters, options);
+            (key, url, Some(cleaned))
         }
+    };
+
+    let found = match (load_cached(&key, options)?, cleaned) {
+        (Some(entry), Some(cleaned)) => Some(choose_search_result(serde_json::from_str(&entry.body)?, &cleaned)),
+        (Some(entry), None) => Some(choose_nearest_result(&entry.body)?),
+        (None, _) => None
+    };
+
+    urls.push(url);
+    let found = found.map(|found| narrow_by_region(found, search.region.as_deref()));
+    let found = match found {
+        Some(FoundLocation::Ambiguous(locs)) if search.pick_best => locs.into_iter().next().map(FoundLocation::Found),
+        found => found
+    };
+    if let Some(FoundLocation::Found(Location { geohash: Some(geohash), .. })) = found {
+        urls.push(format!("{}/weather/forecast/{}", options.base_url, geohash));
     }
-} 
+    Ok(urls)
+}
+
+/// Like `get_location` for a given search term, but never prompts, so ambiguous locations
+/// aren't found unless the best one is picked.
+pub async fn get_location_async(client: &reqwest::Client, term: &str, search: &SearchOptions, options: &FetchOptions) -> Result<Option<Location>> {
+    let possibles = if let Some((latitude, longitude)) = parse_coordinates(term) {
+        nearest_location_async(client, latitude, longitude, options).await?
+    } else if let Some(words) = what3words::parse_address(term) {
+        let (latitude, longitude) = what3words::coordinates_async(client, &words, options).await?;
+        nearest_location_async(client, latitude, longitude, options).await?
+    } else {
+        search_location_async(client, term, &search.filters, options).await?
+    };
+
+    match narrow_by_region(possibles, search.region.as_deref()) {
+        FoundLocation::Found(loc) => Ok(Some(loc.with_coordinates())),
+        FoundLocation::Ambiguous(locs) if search.pick_best => Ok(pick_best(&locs)),
+        FoundLocation::NotFound | FoundLocation::Ambiguous(_) => Ok(None)
+    }
+}
 
-#[derive(Debug, Default, Clone, Serialize)]
+#[derive(Debug, Default, Clone, Serialize, JsonSchema)]
 pub struct Forecast {
-    pub status: String,
-    pub precipitation: f32,
-    pub temperature: f32,
-    pub feels_like: f32,
-    pub wind_speed: f32,
-    pub wind_direction: String,
-    pub wind_gust: f32,
-    pub visibility: f32,
-    pub humidity: f32,
-    pub uv_index: f32
-}
-
-pub fn get_forecast(geohash: String, freedom_units: bool) -> Result<Vec<(NaiveDate, Vec<(NaiveTime, Forecast)>)>> {
-    let convert_temp = |t: f32| if freedom_units {
-        t * 1.8 + 32.0
+    #[schemars(with = "String")]
+    pub status: WeatherStatus,
+    pub precipitation: Option<f32>,
+    pub temperature: Option<f32>,
+    pub feels_like: Option<f32>,
+    pub wind_speed: Option<f32>,
+    pub wind_direction: Option<String>,
+    pub wind_gust: Option<f32>,
+    pub visibility: Option<f32>,
+    pub humidity: Option<f32>,
+    pub uv_index: Option<f32>,
+    pub pressure: Option<f32>,
+    pub snow_probability: Option<f32>,
+    pub snow_amount: Option<f32>
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TideKind {
+    High,
+    Low
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Tide {
+    pub time: NaiveTime,
+    pub kind: TideKind,
+    pub height: f32
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TimeZone {
+    pub abbreviation: String,
+    /// The offset from UTC, such as +01:00.
+    pub utc_offset: String,
+    #[serde(skip)]
+    offset_seconds: i32,
+    /// Whether this is the UK's time zone, whose offset can change during the day.
+    #[serde(skip)]
+    uk: bool
+}
+
+impl TimeZone {
+    pub fn new(abbreviation: &str, offset_seconds: i32) -> TimeZone {
+        let sign = if offset_seconds < 0 { '-' } else { '+' };
+        let minutes = offset_seconds.abs() / 60;
+        let utc_offset = format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60);
+        TimeZone { abbreviation: abbreviation.to_string(), utc_offset, offset_seconds, uk: false }
+    }
+
+    /// A local date and time in this time zone. In the UK's time zone, the offset is the one
+    /// in effect at that time, which differs from the day's on either side of a clock change.
+    pub fn datetime(&self, date: NaiveDate, time: NaiveTime) -> DateTime<FixedOffset> {
+        let offset_seconds = if self.uk { uk_offset(date, time) } else { self.offset_seconds };
+        let utc = date.and_time(time) - Duration::seconds(offset_seconds as i64);
+        DateTime::from_utc(utc, FixedOffset::east(offset_seconds))
+    }
+
+    /// The offset at midday, which is the day's offset even on the UK's clock-change days.
+    pub fn offset(&self) -> FixedOffset {
+        FixedOffset::east(self.offset_seconds)
+    }
+
+    /// The UK's time zone at midday on a date.
+    pub fn uk(date: NaiveDate) -> TimeZone {
+        let timezone = match uk_offset(date, NaiveTime::from_hms(12, 0, 0)) {
+            0 => TimeZone::new("GMT", 0),
+            offset => TimeZone::new("BST", offset)
+        };
+        TimeZone { uk: true, ..timezone }
+    }
+}
+
+/// The UK's offset from UTC at a local time. British Summer Time runs from 01:00 GMT on the last
+/// Sunday in March to 01:00 GMT on the last Sunday in October. Local times between 01:00 and 02:00
+/// happen twice on the October day, and are taken to be the first, in BST.
+fn uk_offset(date: NaiveDate, time: NaiveTime) -> i32 {
+    let last_sunday = |month: u32| {
+        let mut day = NaiveDate::from_ymd(date.year(), month, 31);
+        while day.weekday() != Weekday::Sun {
+            day = day.pred();
+        }
+        day
+    };
+
+    let (start, end) = (last_sunday(3), last_sunday(10));
+    let summer = if date == start {
+        time >= NaiveTime::from_hms(1, 0, 0)
+    } else if date == end {
+        time < NaiveTime::from_hms(2, 0, 0)
     } else {
-        t
+        (start..end).contains(&date)
     };
+    if summer { 3600 } else { 0 }
+}
+
+impl fmt::Display for TimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.offset_seconds < 0 { '-' } else { '+' };
+        let minutes = self.offset_seconds.abs() / 60;
+        match (minutes / 60, minutes % 60) {
+            (0, 0) => write!(f, "{} (UTC)", self.abbreviation),
+            (hours, 0) => write!(f, "{} (UTC{}{})", self.abbreviation, sign, hours),
+            (hours, minutes) => write!(f, "{} (UTC{}{}:{:02})", self.abbreviation, sign, hours, minutes)
+        }
+    }
+}
 
-    let convert_speed = |s: f32| if freedom_units {
-        s * 2.237
+#[derive(Debug, Clone)]
+pub struct ForecastDay {
+    pub date: NaiveDate,
+    pub times: Vec<(NaiveTime, Forecast)>,
+    pub tides: Vec<Tide>,
+    pub sea_temperature: Option<f32>,
+    pub timezone: TimeZone
+}
+
+/// Asks for the British page, which gives temperatures in Celsius and speeds in metres per second
+/// whatever units the site would otherwise pick, since the parser converts from those.
+fn forecast_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static("en-GB"));
+    headers.insert(header::COOKIE, HeaderValue::from_static("units=metric"));
+    headers
+}
+
+pub fn get_forecast(geohash: String, freedom_units: bool, options: &FetchOptions) -> Result<(DateTime<Utc>, Vec<ForecastDay>)> {
+    let url = format!("{}/weather/forecast/{}", options.base_url, geohash);
+    let entry = fetch_from("Met Office", &forecast_key(&geohash, options), url, forecast_headers(), options)?;
+    Ok((entry.fetched_at, parse_forecast(&entry.body, freedom_units, options)?))
+}
+
+pub async fn get_forecast_async(client: &reqwest::Client, geohash: &str, freedom_units: bool, options: &FetchOptions) -> Result<(DateTime<Utc>, Vec<ForecastDay>)> {
+    let url = format!("{}/weather/forecast/{}", options.base_url, geohash);
+    let entry = fetch_async_from(client, "Met Office", &forecast_key(geohash, options), url, forecast_headers(), options).await?;
+    Ok((entry.fetched_at, parse_forecast(&entry.body, freedom_units, options)?))
+}
+
+pub fn get_previous_forecast(geohash: &str, freedom_units: bool, options: &FetchOptions) -> Result<Option<(DateTime<Utc>, Vec<ForecastDay>)>> {
+    let key = forecast_key(geohash, options);
+    match readable(cache::load_previous(options.cache_dir.as_deref(), &key), &key, options)? {
+        Some(entry) => Ok(Some((entry.fetched_at, parse_forecast(&entry.body, freedom_units, options)?))),
+        None => Ok(None)
+    }
+}
+
+/// Selects one element for each forecast time, ignoring the field if there are a different number
+/// of elements, or failing in strict mode.
+fn select_aligned<'a>(day: scraper::ElementRef<'a>, selector: &scraper::Selector, field: &str, date: NaiveDate, count: usize, strict: bool) -> Result<Vec<scraper::ElementRef<'a>>> {
+    let elements: Vec<_> = day.select(selector).collect();
+    if elements.len() == count {
+        Ok(elements)
+    } else if strict {
+        Err(anyhow!("expected {} values of {} on {}, but found {}", count, field, date, elements.len()))
     } else {
-        s * 3.6
+        eprintln!("Warning: expected {} values of {} on {}, but found {} - ignoring them", count, field, date, elements.len());
+        Ok(Vec::new())
+    }
+}
+
+/// The page doesn't give a chance of snow, so it is estimated from the status and the
+/// temperature in Celsius, if known, as a share of the chance of precipitation.
+pub(crate) fn snow_probability(status: &WeatherStatus, precipitation: f32, temperature: Option<f32>) -> f32 {
+    match (status, temperature) {
+        (WeatherStatus::LightSnow | WeatherStatus::HeavySnow, _) => precipitation,
+        (WeatherStatus::Sleet, _) => precipitation / 2.0,
+        (_, Some(temperature)) if temperature <= 0.0 => precipitation / 2.0,
+        (_, Some(temperature)) if temperature <= 2.0 => precipitation / 4.0,
+        _ => 0.0
+    }
+}
+
+/// Reads a percentage cell such as "45%", where "<5%" is taken as none. A cell that can't be read is
+/// missing rather than zero, or fails in strict mode.
+fn parse_percentage(html: &str, field: &str, date: NaiveDate, strict: bool) -> Result<Option<f32>> {
+    let text = html.trim();
+    if text == "&lt;5%" {
+        return Ok(Some(0.0))
+    }
+
+    match text.strip_suffix('%').and_then(|value| value.trim().parse().ok()) {
+        Some(value) => Ok(Some(value)),
+        None if strict => Err(anyhow!("can't read \"{}\" as {} on {}", text, field, date)),
+        None => Ok(None)
+    }
+}
+
+/// Marker elements of the pages the Met Office sometimes serves in place of a forecast.
+const INTERSTITIAL_MARKERS: &[(&str, &str)] = &[
+    ("#onetrust-consent-sdk", "cookie consent"),
+    ("#ccc", "cookie consent"),
+    (".maintenance", "maintenance"),
+    ("#maintenance", "maintenance")
+];
+
+fn detect_interstitial(doc: &scraper::Html) -> Result<Option<&'static str>> {
+    for (marker, kind) in INTERSTITIAL_MARKERS {
+        let selector = scraper::Selector::parse(marker).ok().context("can't parse selector")?;
+        if doc.select(&selector).next().is_some() {
+            return Ok(Some(kind))
+        }
+    }
+
+    let title_selector = scraper::Selector::parse("title").ok().context("can't parse selector")?;
+    let title = doc.select(&title_selector).next().map(|title| title.text().collect::<String>().to_lowercase());
+    Ok(match title {
+        Some(title) if title.contains("maintenance") || title.contains("unavailable") => Some("maintenance"),
+        Some(title) if title.contains("cookie") || title.contains("consent") => Some("cookie consent"),
+        _ => None
+    })
+}
+
+fn parse_forecast(html: &str, freedom_units: bool, options: &FetchOptions) -> Result<Vec<ForecastDay>> {
+    let selectors = &options.selectors;
+    let selector = |custom: &Option<String>, default: &str| {
+        let selector = custom.as_deref().unwrap_or(default);
+        scraper::Selector::parse(selector).ok().with_context(|| format!("can't parse selector {}", selector))
     };
 
-    let day_selector = scraper::Selector::parse(".forecast-day").ok().context("can't parse selector")?;
-    let time_selector = scraper::Selector::parse(".step-time > th[scope=\"col\"]").ok().context("can't parse selector")?;
-    let status_selector = scraper::Selector::parse(".step-symbol > td > img").ok().context("can't parse selector")?;
-    let precip_selector = scraper::Selector::parse(".step-pop > td").ok().context("can't parse selector")?;
-    let temp_selector = scraper::Selector::parse(".step-temp > td > div").ok().context("can't parse selector")?;
-    let feels_selector = scraper::Selector::parse(".step-feels-like > td").ok().context("can't parse selector")?;
-    let wind_speed_selector = scraper::Selector::parse(".step-wind > td > div > .speed").ok().context("can't parse selector")?;
-    let wind_dir_selector = scraper::Selector::parse(".step-wind > td > div > .direction").ok().context("can't parse selector")?;
-    let wind_gust_selector = scraper::Selector::parse(".step-wind-gust > td > .gust").ok().context("can't parse selector")?;
-    let visib_selector = scraper::Selector::parse(".step-visibility > td > .visibility").ok().context("can't parse selector")?;
-    let humid_selector = scraper::Selector::parse(".step-humidity > td").ok().context("can't parse selector")?;
-    let uv_selector = scraper::Selector::parse(".step-uv > td").ok().context("can't parse selector")?;
-
-    let url = format!("https://www.metoffice.gov.uk/weather/forecast/{}", geohash);
-    let html = reqwest::blocking::get(url)?.text()?;
-    let doc = scraper::Html::parse_document(&html);
+    let day_selector = selector(&selectors.day, ".forecast-day")?;
+    let time_selector = selector(&selectors.time, ".step-time > th[scope=\"col\"]")?;
+    let status_selector = selector(&selectors.status, ".step-symbol > td > img")?;
+    let precip_selector = selector(&selectors.precipitation, ".step-pop > td")?;
+    let temp_selector = selector(&selectors.temperature, ".step-temp > td > div")?;
+    let feels_selector = selector(&selectors.feels_like, ".step-feels-like > td")?;
+    let wind_speed_selector = selector(&selectors.wind_speed, ".step-wind > td > div > .speed")?;
+    let wind_dir_selector = selector(&selectors.wind_direction, ".step-wind > td > div > .direction")?;
+    let wind_gust_selector = selector(&selectors.wind_gust, ".step-wind-gust > td > .gust")?;
+    let visib_selector = selector(&selectors.visibility, ".step-visibility > td > .visibility")?;
+    let humid_selector = selector(&selectors.humidity, ".step-humidity > td")?;
+    let uv_selector = selector(&selectors.uv_index, ".step-uv > td")?;
+    let pressure_selector = selector(&selectors.pressure, ".step-pressure > td")?;
+    let snow_selector = selector(&selectors.snow_amount, ".step-snow-amount > td")?;
+    let tide_selector = selector(&selectors.tide, ".tide-times tbody > tr")?;
+    let tide_cell_selector = selector(&selectors.tide_cell, "td")?;
+    let sea_temp_selector = selector(&selectors.sea_temperature, ".sea-temperature")?;
+
+    let doc = scraper::Html::parse_document(html);
+    if doc.select(&day_selector).next().is_none() {
+        if let Some(kind) = detect_interstitial(&doc)? {
+            return Err(anyhow!("the Met Office is showing a {} page instead of the forecast, try again later", kind))
+        }
+    }
 
     let mut results = Vec::new();
     for day in doc.select(&day_selector) {
@@ -221,64 +927,270 @@ pub fn get_forecast(geohash: String, freedom_units: bool) -> Result<Vec<(NaiveDa
 
         let mut forecasts = vec![Forecast::default(); times.len()];
 
-        for (i, status) in day.select(&status_selector).enumerate() {
+        for (i, status) in select_aligned(day, &status_selector, "status", date, times.len(), options.strict)?.into_iter().enumerate() {
             let title = status.value().attr("title").context("can't find title in step-symbol")?;
-            forecasts[i].status = title.to_string();
+            forecasts[i].status = WeatherStatus::from_met_office(title);
         }
 
-        for (i, precip) in day.select(&precip_selector).enumerate() {
-            let inner = precip.inner_html();
-            let text = inner.trim().strip_suffix('%').unwrap_or("0.0");
-            let precip = match text {
-                "&lt;5" => 0.0,
-                _ => text.parse::<f32>()?
-            };
-            forecasts[i].precipitation = precip;
+        for (i, precip) in select_aligned(day, &precip_selector, "precipitation", date, times.len(), options.strict)?.into_iter().enumerate() {
+            forecasts[i].precipitation = parse_percentage(&precip.inner_html(), "precipitation", date, options.strict)?;
         }
 
-        for (i, temp) in day.select(&temp_selector).enumerate() {
+        for (i, temp) in select_aligned(day, &temp_selector, "temperature", date, times.len(), options.strict)?.into_iter().enumerate() {
             let data_value = temp.value().attr("data-value").context("can't find data-value in step-temp")?;
-            forecasts[i].temperature = convert_temp(data_value.parse()?);
+            forecasts[i].temperature = Some(units::temperature(data_value.parse()?, freedom_units));
         }
 
-        for (i, feels) in day.select(&feels_selector).enumerate() {
+        for (i, feels) in select_aligned(day, &feels_selector, "feels_like", date, times.len(), options.strict)?.into_iter().enumerate() {
             let data_value = feels.value().attr("data-value").context("can't find data-value in step-feels-like")?;
-            forecasts[i].feels_like = convert_temp(data_value.parse()?);
+            forecasts[i].feels_like = Some(units::temperature(data_value.parse()?, freedom_units));
         }
         
-        for (i, speed) in day.select(&wind_speed_selector).enumerate() {
+        for (i, speed) in select_aligned(day, &wind_speed_selector, "wind_speed", date, times.len(), options.strict)?.into_iter().enumerate() {
             let data_value = speed.value().attr("data-value").context("can't find data-value in step-wind-speed")?;
-            forecasts[i].wind_speed = convert_speed(data_value.parse()?);
+            forecasts[i].wind_speed = Some(units::speed(data_value.parse()?, freedom_units));
         }
 
-        for (i, dir) in day.select(&wind_dir_selector).enumerate() {
+        for (i, dir) in select_aligned(day, &wind_dir_selector, "wind_direction", date, times.len(), options.strict)?.into_iter().enumerate() {
             let data_value = dir.value().attr("data-value").context("can't find data-value in step-wind-direction")?;
-            forecasts[i].wind_direction = data_value.to_string();
+            forecasts[i].wind_direction = Some(data_value.to_string());
         }
 
-        for (i, gust) in day.select(&wind_gust_selector).enumerate() {
+        for (i, gust) in select_aligned(day, &wind_gust_selector, "wind_gust", date, times.len(), options.strict)?.into_iter().enumerate() {
             let data_value = gust.value().attr("data-value").context("can't find data-value in step-wind-gust")?;
-            forecasts[i].wind_gust = convert_speed(data_value.parse()?);
+            forecasts[i].wind_gust = Some(units::speed(data_value.parse()?, freedom_units));
         }
 
-        for (i, visib) in day.select(&visib_selector).enumerate() {
+        for (i, visib) in select_aligned(day, &visib_selector, "visibility", date, times.len(), options.strict)?.into_iter().enumerate() {
             let data_value = visib.value().attr("data-value").context("can't find data-value in step-visibility")?;
-            forecasts[i].visibility = data_value.parse()?;
+            forecasts[i].visibility = Some(units::distance(data_value.parse()?, freedom_units));
         }
 
-        for (i, humid) in day.select(&humid_selector).enumerate() {
-            let inner = humid.inner_html();
-            let text = inner.trim().strip_suffix('%').unwrap_or("0.0");
-            forecasts[i].humidity = text.parse::<f32>()?;
+        for (i, humid) in select_aligned(day, &humid_selector, "humidity", date, times.len(), options.strict)?.into_iter().enumerate() {
+            forecasts[i].humidity = parse_percentage(&humid.inner_html(), "humidity", date, options.strict)?;
         }
 
-        for (i, uv) in day.select(&uv_selector).enumerate() {
+        for (i, uv) in select_aligned(day, &uv_selector, "uv_index", date, times.len(), options.strict)?.into_iter().enumerate() {
             let data_value = uv.value().attr("data-value").context("can't find data-value in step-uv")?;
-            forecasts[i].uv_index = data_value.parse()?;
+            forecasts[i].uv_index = Some(data_value.parse()?);
         }
 
-        results.push((date, times.into_iter().zip(forecasts).collect()));
+        // Pressure is only shown for some locations, so a missing row isn't a misalignment.
+        if day.select(&pressure_selector).next().is_some() {
+            for (i, pressure) in select_aligned(day, &pressure_selector, "pressure", date, times.len(), options.strict)?.into_iter().enumerate() {
+                let data_value = pressure.value().attr("data-value").context("can't find data-value in step-pressure")?;
+                forecasts[i].pressure = Some(data_value.parse()?);
+            }
+        }
+
+        if day.select(&snow_selector).next().is_some() {
+            for (i, snow) in select_aligned(day, &snow_selector, "snow_amount", date, times.len(), options.strict)?.into_iter().enumerate() {
+                let data_value = snow.value().attr("data-value").context("can't find data-value in step-snow-amount")?;
+                forecasts[i].snow_amount = Some(units::depth(data_value.parse()?, freedom_units));
+            }
+        }
+
+        for forecast in &mut forecasts {
+            let temperature = forecast.temperature.map(|t| units::to_celsius(t, freedom_units));
+            forecast.snow_probability = forecast.precipitation.map(|precipitation| snow_probability(&forecast.status, precipitation, temperature));
+        }
+
+        // Tide tables and sea temperatures are only present on the pages for beach locations.
+        let mut tides = Vec::new();
+        for tide in day.select(&tide_selector) {
+            let cells: Vec<String> = tide.select(&tide_cell_selector).map(|cell| cell.text().collect::<String>().trim().to_string()).collect();
+            let [kind, time, height] = &cells[..] else { continue };
+            let kind = match kind.to_ascii_lowercase().as_str() {
+                "high" => TideKind::High,
+                "low" => TideKind::Low,
+                _ => continue
+            };
+            let time = chrono::NaiveTime::parse_from_str(time, "%H:%M")?;
+            let height = height.strip_suffix('m').unwrap_or(height).trim().parse::<f32>()?;
+            tides.push(Tide { time, kind, height });
+        }
+
+        let sea_temperature = match day.select(&sea_temp_selector).next() {
+            Some(sea_temp) => {
+                let data_value = sea_temp.value().attr("data-value").context("can't find data-value in sea-temperature")?;
+                Some(units::temperature(data_value.parse()?, freedom_units))
+            },
+            None => None
+        };
+
+        results.push(ForecastDay { date, times: times.into_iter().zip(forecasts).collect(), tides, sea_temperature, timezone: TimeZone::uk(date) });
     }
 
     Ok(results)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORECAST: &str = include_str!("../tests/fixtures/forecast.html");
+    const SHORT_UV: &str = include_str!("../tests/fixtures/forecast-short-uv.html");
+
+    fn options(strict: bool) -> FetchOptions {
+        FetchOptions {
+            policy: CachePolicy::Offline,
+            location_policy: CachePolicy::Offline,
+            base_url: String::new(),
+            open_meteo_url: String::new(),
+            what3words_url: String::new(),
+            what3words_key: None,
+            cache_dir: None,
+            selectors: Selectors::default(),
+            strict,
+            store: false,
+            explain: false
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_fixes() {
+        assert!(!is_valid_fix(0.0, 0.0));
+        assert!(!is_valid_fix(91.0, 0.5));
+        assert!(!is_valid_fix(51.5, -181.0));
+        assert!(!is_valid_fix(f32::NAN, -0.1));
+        assert!(!is_valid_fix(51.5, f32::NAN));
+        assert!(is_valid_fix(51.5, -0.1));
+        assert!(is_valid_fix(0.0, -0.1));
+    }
+
+    #[test]
+    fn out_of_range_coordinates_are_rejected() {
+        assert!(nearest_request(200.0, 500.0, &options(false)).is_err());
+        assert!(nearest_request(51.5, -180.5, &options(false)).is_err());
+        assert!(nearest_request(51.5, -0.1, &options(false)).is_ok());
+    }
+
+    #[test]
+    fn uk_offset_changes_with_the_clocks() {
+        let offset = |date: NaiveDate, hour: u32| TimeZone::uk(date).datetime(date, NaiveTime::from_hms(hour, 0, 0)).offset().local_minus_utc();
+        let spring = NaiveDate::from_ymd(2026, 3, 29);
+        assert_eq!(TimeZone::uk(spring).abbreviation, "BST");
+        assert_eq!((offset(spring, 0), offset(spring, 2), offset(spring, 23)), (0, 3600, 3600));
+        let autumn = NaiveDate::from_ymd(2026, 10, 25);
+        assert_eq!(TimeZone::uk(autumn).abbreviation, "GMT");
+        assert_eq!((offset(autumn, 0), offset(autumn, 1), offset(autumn, 2)), (3600, 3600, 0));
+        assert_eq!(offset(NaiveDate::from_ymd(2026, 7, 1), 0), 3600);
+        assert_eq!(offset(NaiveDate::from_ymd(2026, 12, 1), 12), 0);
+    }
+
+    #[test]
+    fn geohash_decodes_to_its_coordinates() {
+        let (latitude, longitude) = decode_geohash("u4pruydqqvj").unwrap();
+        assert!((latitude - 57.64911).abs() < 1e-4 && (longitude - 10.40744).abs() < 1e-4);
+        assert_eq!(decode_geohash("u4pruydqqvA"), decode_geohash("u4pruydqqva"));
+    }
+
+    #[test]
+    fn geohash_with_invalid_characters_is_rejected() {
+        assert_eq!(decode_geohash("gcpvjai"), None);
+        assert_eq!(decode_geohash("gcp-vj"), None);
+        assert_eq!(decode_geohash(""), None);
+    }
+
+    #[test]
+    fn short_column_is_ignored() {
+        let days = parse_forecast(SHORT_UV, false, &options(false)).unwrap();
+        assert!(days[0].times.iter().all(|(_, forecast)| forecast.uv_index.is_none()));
+        assert!(days[0].times.iter().all(|(_, forecast)| forecast.temperature.is_some()));
+    }
+
+    #[test]
+    fn short_column_is_an_error_when_strict() {
+        let err = parse_forecast(SHORT_UV, false, &options(true)).unwrap_err();
+        assert!(err.to_string().contains("uv_index"), "{}", err);
+    }
+
+    #[test]
+    fn parses_temperatures_in_celsius() {
+        let days = parse_forecast(FORECAST, false, &options(true)).unwrap();
+        let temperatures: Vec<f32> = days[0].times.iter().filter_map(|(_, forecast)| forecast.temperature).collect();
+        assert_eq!(temperatures, [-1.5, 3.0, 6.5]);
+        assert!(temperatures.iter().all(|t| (-30.0..=45.0).contains(t)));
+    }
+
+    #[test]
+    fn converts_visibility_to_miles() {
+        let days = parse_forecast(FORECAST, true, &options(true)).unwrap();
+        let visibilities: Vec<f32> = days[0].times.iter().filter_map(|(_, forecast)| forecast.visibility).collect();
+        assert_eq!(visibilities.len(), 3);
+        for (visibility, miles) in visibilities.iter().zip([1.0, 5.0, 10.0]) {
+            assert!((visibility - miles).abs() < 0.001, "{} isn't {} miles", visibility, miles);
+        }
+    }
+
+    #[test]
+    fn unreadable_cache_entries_are_fetched_again_unless_offline() {
+        let dir = std::env::temp_dir().join(format!("weather-unreadable-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("forecast-gcpvj0v07.json"), "{\"fetched_at\": \"2026-01-").unwrap();
+
+        let options = FetchOptions { policy: CachePolicy::Refresh, cache_dir: Some(dir.clone()), ..options(false) };
+        assert!(load_cached("forecast-gcpvj0v07", &options).unwrap().is_none());
+        let offline = FetchOptions { policy: CachePolicy::Offline, ..options };
+        assert!(load_cached("forecast-gcpvj0v07", &offline).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cache_keys_keep_other_servers_apart() {
+        let real = FetchOptions { base_url: DEFAULT_BASE_URL.to_string(), ..options(false) };
+        assert_eq!(forecast_key("gcpvj0v07", &real), "forecast-gcpvj0v07");
+        assert_eq!(search_request("london", &[], &real).0, "search-london-");
+
+        let fixtures = FetchOptions { base_url: "http://127.0.0.1:9999".to_string(), ..real };
+        assert_eq!(forecast_key("gcpvj0v07", &fixtures), "forecast-gcpvj0v07@127.0.0.1:9999");
+        assert_eq!(nearest_request(51.5, -0.1, &fixtures).unwrap().0, "nearest-51.5--0.1@127.0.0.1:9999");
+    }
+
+    #[test]
+    fn unreadable_percentages_are_missing() {
+        let date = NaiveDate::from_ymd(2026, 1, 14);
+        assert_eq!(parse_percentage(" 45% ", "humidity", date, true).unwrap(), Some(45.0));
+        assert_eq!(parse_percentage("&lt;5%", "precipitation", date, true).unwrap(), Some(0.0));
+        assert_eq!(parse_percentage("-", "precipitation", date, false).unwrap(), None);
+        assert_eq!(parse_percentage("", "humidity", date, false).unwrap(), None);
+        assert!(parse_percentage("-", "precipitation", date, true).is_err());
+    }
+
+    #[test]
+    fn detects_interstitial_pages() {
+        let detect = |html: &str| detect_interstitial(&scraper::Html::parse_document(html)).unwrap();
+        assert_eq!(detect("<html><body><div id=\"onetrust-consent-sdk\"></div></body></html>"), Some("cookie consent"));
+        assert_eq!(detect("<html><body><div class=\"maintenance\"></div></body></html>"), Some("maintenance"));
+        assert_eq!(detect("<html><head><title>Service Unavailable</title></head></html>"), Some("maintenance"));
+        assert_eq!(detect("<html><head><title>Your cookie choices</title></head></html>"), Some("cookie consent"));
+        assert_eq!(detect(FORECAST), None);
+    }
+
+    #[test]
+    fn regions_narrow_ambiguous_locations() {
+        let loc = |area: Option<&str>| Location { name: "Newport".to_string(), area: area.map(str::to_string), geohash: None, latitude: None, longitude: None };
+        let found = || FoundLocation::Ambiguous(vec![loc(Some("Newport, Wales")), loc(Some("Isle of Wight")), loc(None), loc(Some("Shropshire, England"))]);
+        let areas = |found: FoundLocation| match found {
+            FoundLocation::Found(loc) => vec![loc.area],
+            FoundLocation::Ambiguous(locs) => locs.into_iter().map(|loc| loc.area).collect(),
+            FoundLocation::NotFound => Vec::new()
+        };
+
+        assert!(matches!(narrow_by_region(found(), Some(" WALES ")), FoundLocation::Found(_)));
+        assert_eq!(areas(narrow_by_region(found(), Some("Wales"))), [Some("Newport, Wales".to_string())]);
+        assert_eq!(areas(narrow_by_region(found(), Some("o"))).len(), 3);
+        assert_eq!(areas(narrow_by_region(found(), Some("Scotland"))).len(), 4);
+        assert_eq!(areas(narrow_by_region(found(), None)).len(), 4);
+    }
+
+    #[test]
+    fn snow_probability_depends_on_status_and_temperature() {
+        assert_eq!(snow_probability(&WeatherStatus::HeavySnow, 80.0, Some(5.0)), 80.0);
+        assert_eq!(snow_probability(&WeatherStatus::Sleet, 80.0, None), 40.0);
+        assert_eq!(snow_probability(&WeatherStatus::LightRain, 80.0, Some(-1.0)), 40.0);
+        assert_eq!(snow_probability(&WeatherStatus::LightRain, 80.0, Some(2.0)), 20.0);
+        assert_eq!(snow_probability(&WeatherStatus::LightRain, 80.0, Some(2.5)), 0.0);
+        assert_eq!(snow_probability(&WeatherStatus::Cloudy, 80.0, None), 0.0);
+    }
+}
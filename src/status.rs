@@ -0,0 +1,378 @@
+use serde::{Serialize, Serializer};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WeatherStatus {
+    ClearNight,
+    SunnyDay,
+    PartlyCloudyNight,
+    PartlyCloudyDay,
+    SunnyIntervals,
+    Cloudy,
+    Overcast,
+    LightShowerNight,
+    LightShowerDay,
+    HeavyShowerNight,
+    HeavyShowerDay,
+    LightRain,
+    HeavyRain,
+    Sleet,
+    LightSnow,
+    HeavySnow,
+    ThunderShowerNight,
+    ThunderShowerDay,
+    Other(String)
+}
+
+/// Broad groups of statuses, for when the details don't matter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Condition {
+    Clear,
+    ClearNight,
+    PartlyCloudy,
+    Cloudy,
+    Rain,
+    Snow,
+    Thunder,
+    Unknown
+}
+
+impl Default for WeatherStatus {
+    fn default() -> WeatherStatus {
+        WeatherStatus::Other(String::new())
+    }
+}
+
+impl WeatherStatus {
+    pub fn from_met_office(title: &str) -> WeatherStatus {
+        match title {
+            "Clear night" => WeatherStatus::ClearNight,
+            "Sunny day" => WeatherStatus::SunnyDay,
+            "Partly cloudy (night)" => WeatherStatus::PartlyCloudyNight,
+            "Partly cloudy (day)" => WeatherStatus::PartlyCloudyDay,
+            "Sunny intervals" => WeatherStatus::SunnyIntervals,
+            "Cloudy" => WeatherStatus::Cloudy,
+            "Overcast" => WeatherStatus::Overcast,
+            "Light shower (night)" => WeatherStatus::LightShowerNight,
+            "Light shower (day)" => WeatherStatus::LightShowerDay,
+            "Heavy shower (night)" => WeatherStatus::HeavyShowerNight,
+            "Heavy shower (day)" => WeatherStatus::HeavyShowerDay,
+            "Light rain" => WeatherStatus::LightRain,
+            "Heavy rain" => WeatherStatus::HeavyRain,
+            "Sleet" => WeatherStatus::Sleet,
+            "Light snow" => WeatherStatus::LightSnow,
+            "Heavy snow" => WeatherStatus::HeavySnow,
+            "Thunder shower (night)" => WeatherStatus::ThunderShowerNight,
+            "Thunder shower (day)" => WeatherStatus::ThunderShowerDay,
+            title => WeatherStatus::Other(title.to_string())
+        }
+    }
+
+    /// Maps a WMO weather interpretation code, as used by Open-Meteo, onto the nearest Met Office status.
+    pub fn from_wmo(code: u8, is_day: bool) -> WeatherStatus {
+        match (code, is_day) {
+            (0, true) => WeatherStatus::SunnyDay,
+            (0, false) => WeatherStatus::ClearNight,
+            (1, true) => WeatherStatus::SunnyIntervals,
+            (1 | 2, false) => WeatherStatus::PartlyCloudyNight,
+            (2, true) => WeatherStatus::PartlyCloudyDay,
+            (3, _) => WeatherStatus::Overcast,
+            (45 | 48, _) => WeatherStatus::Other("Fog".to_string()),
+            (51 | 53 | 55 | 61, _) => WeatherStatus::LightRain,
+            (63 | 65, _) => WeatherStatus::HeavyRain,
+            (56 | 57 | 66 | 67, _) => WeatherStatus::Sleet,
+            (71 | 77 | 85, _) => WeatherStatus::LightSnow,
+            (73 | 75 | 86, _) => WeatherStatus::HeavySnow,
+            (80, true) => WeatherStatus::LightShowerDay,
+            (80, false) => WeatherStatus::LightShowerNight,
+            (81 | 82, true) => WeatherStatus::HeavyShowerDay,
+            (81 | 82, false) => WeatherStatus::HeavyShowerNight,
+            (95 | 96 | 99, true) => WeatherStatus::ThunderShowerDay,
+            (95 | 96 | 99, false) => WeatherStatus::ThunderShowerNight,
+            (code, _) => WeatherStatus::Other(format!("Weather code {}", code))
+        }
+    }
+
+    /// A name for the status that stays the same whatever the language or wording of its label.
+    pub fn id(&self) -> &str {
+        match self {
+            WeatherStatus::ClearNight => "clear_night",
+            WeatherStatus::SunnyDay => "sunny_day",
+            WeatherStatus::PartlyCloudyNight => "partly_cloudy_night",
+            WeatherStatus::PartlyCloudyDay => "partly_cloudy_day",
+            WeatherStatus::SunnyIntervals => "sunny_intervals",
+            WeatherStatus::Cloudy => "cloudy",
+            WeatherStatus::Overcast => "overcast",
+            WeatherStatus::LightShowerNight => "light_shower_night",
+            WeatherStatus::LightShowerDay => "light_shower_day",
+            WeatherStatus::HeavyShowerNight => "heavy_shower_night",
+            WeatherStatus::HeavyShowerDay => "heavy_shower_day",
+            WeatherStatus::LightRain => "light_rain",
+            WeatherStatus::HeavyRain => "heavy_rain",
+            WeatherStatus::Sleet => "sleet",
+            WeatherStatus::LightSnow => "light_snow",
+            WeatherStatus::HeavySnow => "heavy_snow",
+            WeatherStatus::ThunderShowerNight => "thunder_shower_night",
+            WeatherStatus::ThunderShowerDay => "thunder_shower_day",
+            WeatherStatus::Other(title) => title
+        }
+    }
+
+    /// The label and short code for the status in a language, or in English if it has no
+    /// translation. Statuses that aren't known are shown as they were given.
+    fn translation(&self, language: &str) -> Option<(&'static str, &'static str)> {
+        if let WeatherStatus::Other(_) = self {
+            return None
+        }
+        let find = |language: &str| TRANSLATIONS.iter()
+            .find(|(code, _)| *code == language)
+            .and_then(|(_, statuses)| statuses.iter().find(|(id, _, _)| *id == self.id()))
+            .map(|(_, label, short_code)| (*label, *short_code));
+        find(language).or_else(|| find("en"))
+    }
+
+    /// The English label, which is also how the status is given in structured output.
+    pub fn label(&self) -> &str {
+        self.label_in("en")
+    }
+
+    pub fn label_in(&self, language: &str) -> &str {
+        self.translation(language).map_or(self.id(), |(label, _)| label)
+    }
+
+    pub fn icon(&self) -> &str {
+        match self {
+            WeatherStatus::Cloudy | WeatherStatus::Overcast => "☁",
+            WeatherStatus::LightShowerNight | WeatherStatus::LightShowerDay
+                | WeatherStatus::HeavyShowerNight | WeatherStatus::HeavyShowerDay => "🌧",
+            WeatherStatus::PartlyCloudyNight | WeatherStatus::PartlyCloudyDay => "🌥",
+            WeatherStatus::SunnyDay => "☀",
+            WeatherStatus::ClearNight => "☾",
+            WeatherStatus::LightSnow | WeatherStatus::HeavySnow => "☃",
+            WeatherStatus::SunnyIntervals => "🌤",
+            WeatherStatus::LightRain | WeatherStatus::HeavyRain => "☂",
+            WeatherStatus::Sleet => "🌨",
+            WeatherStatus::ThunderShowerNight | WeatherStatus::ThunderShowerDay => "☈",
+            WeatherStatus::Other(title) => title
+        }
+    }
+
+    pub fn short_code_in(&self, language: &str) -> &str {
+        self.translation(language).map_or(self.id(), |(_, short_code)| short_code)
+    }
+}
+
+/// A status id, with its label and short code.
+type Translation = (&'static str, &'static str, &'static str);
+
+/// The label and short code of each status, for each language.
+const TRANSLATIONS: &[(&str, &[Translation])] = &[
+    ("en", &[
+        ("clear_night", "Clear night", "CN"),
+        ("sunny_day", "Sunny day", "SU"),
+        ("partly_cloudy_night", "Partly cloudy (night)", "PC"),
+        ("partly_cloudy_day", "Partly cloudy (day)", "PC"),
+        ("sunny_intervals", "Sunny intervals", "PC"),
+        ("cloudy", "Cloudy", "CL"),
+        ("overcast", "Overcast", "CL"),
+        ("light_shower_night", "Light shower (night)", "SH"),
+        ("light_shower_day", "Light shower (day)", "SH"),
+        ("heavy_shower_night", "Heavy shower (night)", "SH"),
+        ("heavy_shower_day", "Heavy shower (day)", "SH"),
+        ("light_rain", "Light rain", "RA"),
+        ("heavy_rain", "Heavy rain", "RA"),
+        ("sleet", "Sleet", "SL"),
+        ("light_snow", "Light snow", "SN"),
+        ("heavy_snow", "Heavy snow", "SN"),
+        ("thunder_shower_night", "Thunder shower (night)", "TH"),
+        ("thunder_shower_day", "Thunder shower (day)", "TH")
+    ]),
+    ("fr", &[
+        ("clear_night", "Nuit claire", "NC"),
+        ("sunny_day", "Journée ensoleillée", "SO"),
+        ("partly_cloudy_night", "Partiellement nuageux (nuit)", "PN"),
+        ("partly_cloudy_day", "Partiellement nuageux (jour)", "PN"),
+        ("sunny_intervals", "Éclaircies", "PN"),
+        ("cloudy", "Nuageux", "NU"),
+        ("overcast", "Couvert", "NU"),
+        ("light_shower_night", "Averse légère (nuit)", "AV"),
+        ("light_shower_day", "Averse légère (jour)", "AV"),
+        ("heavy_shower_night", "Forte averse (nuit)", "AV"),
+        ("heavy_shower_day", "Forte averse (jour)", "AV"),
+        ("light_rain", "Pluie légère", "PL"),
+        ("heavy_rain", "Forte pluie", "PL"),
+        ("sleet", "Neige fondue", "NF"),
+        ("light_snow", "Neige légère", "NE"),
+        ("heavy_snow", "Fortes chutes de neige", "NE"),
+        ("thunder_shower_night", "Averse orageuse (nuit)", "OR"),
+        ("thunder_shower_day", "Averse orageuse (jour)", "OR")
+    ])
+];
+
+impl WeatherStatus {
+    pub fn condition(&self) -> Condition {
+        match self {
+            WeatherStatus::SunnyDay => Condition::Clear,
+            WeatherStatus::ClearNight => Condition::ClearNight,
+            WeatherStatus::PartlyCloudyNight | WeatherStatus::PartlyCloudyDay | WeatherStatus::SunnyIntervals => Condition::PartlyCloudy,
+            WeatherStatus::Cloudy | WeatherStatus::Overcast => Condition::Cloudy,
+            WeatherStatus::LightShowerNight | WeatherStatus::LightShowerDay | WeatherStatus::HeavyShowerNight
+                | WeatherStatus::HeavyShowerDay | WeatherStatus::LightRain | WeatherStatus::HeavyRain => Condition::Rain,
+            WeatherStatus::Sleet | WeatherStatus::LightSnow | WeatherStatus::HeavySnow => Condition::Snow,
+            WeatherStatus::ThunderShowerNight | WeatherStatus::ThunderShowerDay => Condition::Thunder,
+            WeatherStatus::Other(_) => Condition::Unknown
+        }
+    }
+}
+
+impl Condition {
+    /// A few lines of art depicting the condition, using only ASCII characters if asked, and
+    /// without the snowflakes and lightning of emoji fonts unless `emoji` is set.
+    pub fn art(self, ascii: bool, emoji: bool) -> &'static [&'static str] {
+        match (self, ascii) {
+            (Condition::Clear, true) => &[
+                r"    \   /    ",
+                r"     .-.     ",
+                r"  - (   ) -  ",
+                r"     `-'     ",
+                r"    /   \    "
+            ],
+            (Condition::Clear, false) => &[
+                r"    \   /    ",
+                r"     .-.     ",
+                r"  ― (   ) ―  ",
+                r"     `-’     ",
+                r"    /   \    "
+            ],
+            (Condition::ClearNight, true) => &[
+                r"     _      * ",
+                r"    / )  *    ",
+                r"   ( (        ",
+                r"    \_)    *  ",
+                r"              "
+            ],
+            (Condition::ClearNight, false) => &[
+                r"     _      ✦ ",
+                r"    / )  ✧    ",
+                r"   ( (        ",
+                r"    \_)    ✦  ",
+                r"              "
+            ],
+            (Condition::PartlyCloudy, true) => &[
+                r"   \  /       ",
+                r" _ /''.-.     ",
+                r"   \_(   ).   ",
+                r"   /(___(__)  ",
+                r"              "
+            ],
+            (Condition::PartlyCloudy, false) => &[
+                r"   \  /       ",
+                r" _ /‘‘.-.     ",
+                r"   \_(░░░).   ",
+                r"   /(___(__)  ",
+                r"              "
+            ],
+            (Condition::Cloudy, true) => &[
+                r"              ",
+                r"     .--.     ",
+                r"  .-(    ).   ",
+                r" (___.__)__)  ",
+                r"              "
+            ],
+            (Condition::Cloudy, false) => &[
+                r"              ",
+                r"     .--.     ",
+                r"  .-(░░░░).   ",
+                r" (___.__)__)  ",
+                r"              "
+            ],
+            (Condition::Rain, true) => &[
+                r"     .-.      ",
+                r"    (   ).    ",
+                r"   (___(__)   ",
+                r"    ' ' ' '   ",
+                r"   ' ' ' '    "
+            ],
+            (Condition::Rain, false) => &[
+                r"     .-.      ",
+                r"    (▒▒▒).    ",
+                r"   (___(__)   ",
+                r"    ‚‘‚‘‚‘    ",
+                r"   ‚‘‚‘‚‘     "
+            ],
+            (Condition::Snow, true) => &[
+                r"     .-.      ",
+                r"    (   ).    ",
+                r"   (___(__)   ",
+                r"    *  *  *   ",
+                r"   *  *  *    "
+            ],
+            (Condition::Snow, false) if !emoji => &[
+                r"     .-.      ",
+                r"    (▒▒▒).    ",
+                r"   (___(__)   ",
+                r"    *  *  *   ",
+                r"   *  *  *    "
+            ],
+            (Condition::Snow, false) => &[
+                r"     .-.      ",
+                r"    (▒▒▒).    ",
+                r"   (___(__)   ",
+                r"    ❄  ❄  ❄   ",
+                r"   ❄  ❄  ❄    "
+            ],
+            (Condition::Thunder, true) => &[
+                r"     .-.      ",
+                r"    (   ).    ",
+                r"   (___(__)   ",
+                r"     /_ /_    ",
+                r"      /  /    "
+            ],
+            (Condition::Thunder, false) if !emoji => &[
+                r"     .-.      ",
+                r"    (▓▓▓).    ",
+                r"   (___(__)   ",
+                r"    ϟ‚‘ϟ‚‘    ",
+                r"    ‚‘ϟ‚‘ϟ    "
+            ],
+            (Condition::Thunder, false) => &[
+                r"     .-.      ",
+                r"    (▓▓▓).    ",
+                r"   (___(__)   ",
+                r"    ⚡‚‘⚡‚‘    ",
+                r"    ‚‘⚡‚‘⚡    "
+            ],
+            (Condition::Unknown, _) => &[]
+        }
+    }
+}
+
+impl Serialize for WeatherStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.label())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn art_has_no_emoji_unless_asked() {
+        for condition in [Condition::Snow, Condition::Thunder] {
+            assert!(condition.art(false, false).iter().all(|line| !line.contains(['❄', '⚡'])));
+            assert!(condition.art(false, true).iter().any(|line| line.contains(['❄', '⚡'])));
+        }
+    }
+
+    #[test]
+    fn labels_fall_back_to_english() {
+        assert_eq!(WeatherStatus::HeavyRain.label(), "Heavy rain");
+        assert_eq!((WeatherStatus::HeavyRain.label_in("fr"), WeatherStatus::HeavyRain.short_code_in("fr")), ("Forte pluie", "PL"));
+        assert_eq!((WeatherStatus::HeavyRain.label_in("de"), WeatherStatus::HeavyRain.short_code_in("de")), ("Heavy rain", "RA"));
+        let fog = WeatherStatus::Other("Fog".to_string());
+        assert_eq!((fog.label_in("fr"), fog.short_code_in("en")), ("Fog", "Fog"));
+        for status in [WeatherStatus::ClearNight, WeatherStatus::SunnyIntervals, WeatherStatus::ThunderShowerDay] {
+            assert_eq!(WeatherStatus::from_met_office(status.label()), status);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use chrono::{NaiveDate, DateTime, Utc, TimeZone};
+
+const UNIX_EPOCH_JULIAN: f64 = 2440587.5;
+const J2000: f64 = 2451545.0;
+
+fn julian_to_utc(julian: f64) -> DateTime<Utc> {
+    let seconds = (julian - UNIX_EPOCH_JULIAN) * 86400.0;
+    Utc.timestamp(seconds as i64, 0)
+}
+
+/// Sunrise and sunset on the given date, using the standard sunrise equation.
+/// Returns None during polar day or night, when the sun doesn't rise or set.
+pub fn sun_times(date: NaiveDate, latitude: f32, longitude: f32) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let latitude = (latitude as f64).to_radians();
+    let longitude = longitude as f64;
+
+    let noon = date.and_hms(12, 0, 0).timestamp() as f64 / 86400.0 + UNIX_EPOCH_JULIAN;
+    let solar_noon = (noon - J2000 + 0.0008).round() - longitude / 360.0;
+
+    let anomaly = (357.5291 + 0.98560028 * solar_noon).rem_euclid(360.0).to_radians();
+    let centre = 1.9148 * anomaly.sin() + 0.02 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+    let ecliptic = (anomaly.to_degrees() + centre + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+    let transit = J2000 + solar_noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic).sin();
+
+    let declination = (ecliptic.sin() * 23.4397f64.to_radians().sin()).asin();
+    let cos_hour_angle = ((-0.833f64).to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None
+    }
+
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+    Some((julian_to_utc(transit - hour_angle / 360.0), julian_to_utc(transit + hour_angle / 360.0)))
+}
@@ -0,0 +1,156 @@
+use std::path::Path;
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+
+/// A piece of a parsed template.
+#[derive(Debug)]
+enum Part {
+    Text(String),
+    /// A dotted path to a value, and the number of decimal places to show it with, if given.
+    Variable(String, Option<usize>),
+    /// Repeated for each item of a list, or shown once for any other value that isn't null, false or missing.
+    Section(String, Vec<Part>)
+}
+
+/// A minimal text template, with `{{path.to.value}}` variables, `{{path:1}}` to give a number's
+/// decimal places, and `{{#path}}...{{/path}}` sections.
+#[derive(Debug)]
+pub struct Template {
+    parts: Vec<Part>
+}
+
+impl Template {
+    pub fn load(path: &Path) -> Result<Template> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("can't read template {}", path.display()))?;
+        Template::parse(&text).with_context(|| format!("malformed template {}", path.display()))
+    }
+
+    fn parse(text: &str) -> Result<Template> {
+        let mut stack: Vec<(String, Vec<Part>)> = vec![(String::new(), Vec::new())];
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let (before, tag) = rest.split_at(start);
+            let end = tag.find("}}").context("unclosed {{")?;
+            if !before.is_empty() {
+                stack.last_mut().context("unbalanced sections")?.1.push(Part::Text(before.to_string()));
+            }
+
+            let name = tag[2..end].trim();
+            rest = &tag[end + 2..];
+            if let Some(name) = name.strip_prefix('#') {
+                stack.push((name.trim().to_string(), Vec::new()));
+            } else if let Some(name) = name.strip_prefix('/') {
+                let (open, body) = stack.pop().context("unbalanced sections")?;
+                if stack.is_empty() || open != name.trim() {
+                    return Err(anyhow!("{{{{/{}}}}} doesn't close the open section", name.trim()))
+                }
+                stack.last_mut().context("unbalanced sections")?.1.push(Part::Section(open, body));
+            } else {
+                let (path, places) = match name.split_once(':') {
+                    Some((path, places)) => (path, Some(places.trim().parse().context("expected a number of decimal places")?)),
+                    None => (name, None)
+                };
+                stack.last_mut().context("unbalanced sections")?.1.push(Part::Variable(path.trim().to_string(), places));
+            }
+        }
+
+        let (open, mut parts) = stack.pop().context("unbalanced sections")?;
+        if !stack.is_empty() {
+            return Err(anyhow!("section {{{{#{}}}}} is never closed", open))
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        Ok(Template { parts })
+    }
+
+    pub fn render(&self, context: &Value) -> String {
+        let mut out = String::new();
+        render_parts(&self.parts, &mut vec![context], &mut out);
+        out
+    }
+}
+
+/// Looks a path up in the innermost scope that has its first part.
+fn lookup<'a>(scopes: &[&'a Value], path: &str) -> Option<&'a Value> {
+    let mut keys = path.split('.');
+    let first = keys.next()?;
+    let value = scopes.iter().rev().find_map(|scope| scope.get(first))?;
+    keys.try_fold(value, |value, key| value.get(key))
+}
+
+fn render_parts<'a>(parts: &'a [Part], scopes: &mut Vec<&'a Value>, out: &mut String) {
+    for part in parts {
+        match part {
+            Part::Text(text) => out.push_str(text),
+            Part::Variable(path, places) => out.push_str(&match (lookup(scopes, path), places) {
+                (None, _) => String::new(),
+                (Some(Value::Null), _) => "-".to_string(),
+                (Some(Value::String(s)), _) => s.clone(),
+                (Some(Value::Number(n)), Some(places)) => format!("{:.*}", places, n.as_f64().unwrap_or_default()),
+                // Converted values can have spurious digits, so are rounded to hundredths.
+                (Some(Value::Number(n)), None) if n.is_f64() => ((n.as_f64().unwrap_or_default() * 100.0).round() / 100.0).to_string(),
+                (Some(value), _) => value.to_string()
+            }),
+            Part::Section(path, body) => match lookup(scopes, path) {
+                None | Some(Value::Null) | Some(Value::Bool(false)) => (),
+                Some(Value::Array(items)) => for item in items {
+                    scopes.push(item);
+                    render_parts(body, scopes, out);
+                    scopes.pop();
+                },
+                Some(value) => {
+                    scopes.push(value);
+                    render_parts(body, scopes, out);
+                    scopes.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(template: &str, context: Value) -> String {
+        Template::parse(template).unwrap().render(&context)
+    }
+
+    #[test]
+    fn renders_variables() {
+        let context = json!({ "location": { "name": "London" }, "wind": 10.799999, "missing": null, "dry": true });
+        assert_eq!(render("{{ location.name }}: {{wind}} {{missing}} {{dry}}{{nowhere}}", context), "London: 10.8 - true");
+    }
+
+    #[test]
+    fn renders_numbers_to_the_given_places() {
+        let context = json!({ "temperature": 6.25, "count": 3 });
+        assert_eq!(render("{{temperature:1}} {{temperature:0}} {{count:2}}", context), "6.2 6 3.00");
+        assert!(Template::parse("{{temperature:a}}").is_err());
+    }
+
+    #[test]
+    fn renders_nested_sections() {
+        let context = json!({
+            "name": "London",
+            "days": [
+                { "date": "Mon", "times": [{ "hour": 9 }, { "hour": 12 }] },
+                { "date": "Tue", "times": [], "warning": { "level": "amber" } }
+            ]
+        });
+        let template = "{{#days}}{{date}} {{name}}:{{#times}} {{hour}}{{/times}}{{#warning}} {{level}}{{/warning}};{{/days}}";
+        assert_eq!(render(template, context), "Mon London: 9 12;Tue London: amber;");
+    }
+
+    #[test]
+    fn unbalanced_sections_are_errors() {
+        let unclosed = Template::parse("{{#days}}{{date}}").unwrap_err();
+        assert!(unclosed.to_string().contains("{{#days}} is never closed"), "{}", unclosed);
+        let mismatched = Template::parse("{{#days}}{{#times}}{{/days}}{{/times}}").unwrap_err();
+        assert!(mismatched.to_string().contains("{{/days}} doesn't close the open section"), "{}", mismatched);
+        assert!(Template::parse("{{/days}}").is_err());
+        assert!(Template::parse("{{date").unwrap_err().to_string().contains("unclosed"));
+    }
+}
@@ -0,0 +1,82 @@
+// Forecasts are read in metric units, and converted to the chosen unit system with these.
+const KPH_PER_MS: f32 = 3.6;
+const KPH_PER_MPH: f32 = 1.609344;
+const CM_PER_INCH: f32 = 2.54;
+const METRES_PER_MILE: f32 = 1609.344;
+
+pub fn fahrenheit(celsius: f32) -> f32 {
+    celsius * 1.8 + 32.0
+}
+
+pub fn celsius(fahrenheit: f32) -> f32 {
+    (fahrenheit - 32.0) / 1.8
+}
+
+pub fn mph(kph: f32) -> f32 {
+    kph / KPH_PER_MPH
+}
+
+pub fn kph(mph: f32) -> f32 {
+    mph * KPH_PER_MPH
+}
+
+/// A temperature in Celsius, in the chosen unit system.
+pub fn temperature(celsius: f32, freedom_units: bool) -> f32 {
+    if freedom_units { fahrenheit(celsius) } else { celsius }
+}
+
+/// A difference between temperatures in Celsius, in the chosen unit system.
+pub fn temperature_difference(celsius: f32, freedom_units: bool) -> f32 {
+    if freedom_units { celsius * 1.8 } else { celsius }
+}
+
+/// A speed in metres per second, in miles or kilometres per hour.
+pub fn speed(ms: f32, freedom_units: bool) -> f32 {
+    let kph = ms * KPH_PER_MS;
+    if freedom_units { mph(kph) } else { kph }
+}
+
+/// A depth in centimetres, in inches or centimetres.
+pub fn depth(cm: f32, freedom_units: bool) -> f32 {
+    if freedom_units { cm / CM_PER_INCH } else { cm }
+}
+
+/// A distance in metres, in miles or metres.
+pub fn distance(metres: f32, freedom_units: bool) -> f32 {
+    if freedom_units { metres / METRES_PER_MILE } else { metres }
+}
+
+/// A temperature in the chosen unit system, back in Celsius.
+pub fn to_celsius(temperature: f32, freedom_units: bool) -> f32 {
+    if freedom_units { celsius(temperature) } else { temperature }
+}
+
+/// A speed in the chosen unit system, back in kilometres per hour.
+pub fn to_kph(speed: f32, freedom_units: bool) -> f32 {
+    if freedom_units { kph(speed) } else { speed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.001
+    }
+
+    #[test]
+    fn converts_to_us_customary_units() {
+        assert!(close(temperature(-40.0, true), -40.0) && close(temperature(100.0, true), 212.0));
+        assert!(close(speed(10.0, false), 36.0) && close(speed(10.0, true), 22.3694));
+        assert!(close(depth(2.54, true), 1.0));
+        assert!(close(distance(8046.72, true), 5.0) && close(distance(8046.72, false), 8046.72));
+        assert!(close(temperature_difference(3.0, true), 5.4));
+    }
+
+    #[test]
+    fn converts_back_to_metric() {
+        assert!(close(to_celsius(temperature(21.5, true), true), 21.5));
+        assert!(close(to_kph(speed(10.0, true), true), 36.0));
+        assert!(close(to_celsius(21.5, false), 21.5) && close(to_kph(36.0, false), 36.0));
+    }
+}
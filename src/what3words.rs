@@ -0,0 +1,74 @@
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use reqwest::header::{HeaderMap, HeaderValue};
+use crate::raw::{self, FetchOptions};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.what3words.com";
+
+#[derive(Deserialize, Debug)]
+struct Response {
+    coordinates: Coordinates
+}
+
+#[derive(Deserialize, Debug)]
+struct Coordinates {
+    lat: f32,
+    lng: f32
+}
+
+/// The three words of a what3words address written as ///word.word.word, in lowercase.
+pub fn parse_address(term: &str) -> Option<String> {
+    let address_regex = regex::Regex::new(r"^\s*///(\p{L}+\.\p{L}+\.\p{L}+)\s*$").ok()?;
+    let captures = address_regex.captures(term)?;
+    Some(captures.get(1)?.as_str().to_lowercase())
+}
+
+/// The cache key, URL and headers of a conversion. The API key is sent as a header rather than in
+/// the URL, so that it isn't shown in errors, which include the URL.
+fn request(words: &str, options: &FetchOptions) -> Result<(String, String, HeaderMap)> {
+    let key = options.what3words_key.as_deref()
+        .context("A what3words API key is needed to find what3words addresses. Set WHAT3WORDS_API_KEY or what3words_key in the config file.")?;
+    let mut key = HeaderValue::from_str(key).context("malformed what3words API key")?;
+    key.set_sensitive(true);
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Api-Key", key);
+    Ok((cache_key(words, options), url(words, options), headers))
+}
+
+pub fn url(words: &str, options: &FetchOptions) -> String {
+    format!("{}/v3/convert-to-coordinates?words={}", options.what3words_url, urlencoding::encode(words))
+}
+
+/// The coordinates of a what3words address from an earlier conversion, without using the network.
+pub fn cached_coordinates(words: &str, options: &FetchOptions) -> Result<Option<(f32, f32)>> {
+    raw::load_cached(&cache_key(words, options), options)?
+        .map(|entry| parse_response(&entry.body))
+        .transpose()
+}
+
+fn cache_key(words: &str, options: &FetchOptions) -> String {
+    raw::cache_key(format!("what3words-{}", words), &options.what3words_url, DEFAULT_BASE_URL)
+}
+
+fn parse_response(body: &str) -> Result<(f32, f32)> {
+    let response: Response = serde_json::from_str(body).context("malformed what3words response")?;
+    let Coordinates { lat, lng } = response.coordinates;
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lng) {
+        return Err(anyhow!("what3words gave coordinates out of range"))
+    }
+    Ok((lat, lng))
+}
+
+/// Finds the coordinates of a what3words address. Addresses never move, so they are cached
+/// for as long as location searches are.
+pub fn coordinates(words: &str, options: &FetchOptions) -> Result<(f32, f32)> {
+    let (key, url, headers) = request(words, options)?;
+    let entry = raw::fetch_from("what3words", &key, url, headers, &FetchOptions { policy: options.location_policy, ..options.clone() })?;
+    parse_response(&entry.body)
+}
+
+pub async fn coordinates_async(client: &reqwest::Client, words: &str, options: &FetchOptions) -> Result<(f32, f32)> {
+    let (key, url, headers) = request(words, options)?;
+    let entry = raw::fetch_async_from(client, "what3words", &key, url, headers, &FetchOptions { policy: options.location_policy, ..options.clone() }).await?;
+    parse_response(&entry.body)
+}